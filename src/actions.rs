@@ -0,0 +1,322 @@
+use crate::config::Dir;
+use crate::gamepad::{self, GamepadStick};
+use crate::settings::Settings;
+use crate::touch::TouchInput;
+use bevy::input::InputSystems;
+use bevy::input::keyboard::Key;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Actions>()
+            .init_resource::<Rebinding>()
+            .add_systems(
+                PreUpdate,
+                collect_actions
+                    .after(InputSystems)
+                    .after(gamepad::update_sticks),
+            );
+    }
+}
+
+/// Everything the player can do, independent of the key or button it is bound to.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Action {
+    TurnUp,
+    TurnDown,
+    TurnLeft,
+    TurnRight,
+    /// The diagonal turns, which only steer on a hex grid.
+    TurnUpLeft,
+    TurnUpRight,
+    TurnDownLeft,
+    TurnDownRight,
+    Pause,
+    Restart,
+    Quit,
+    /// Turns all sound on or off.
+    Mute,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::TurnUp,
+        Action::TurnDown,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::TurnUpLeft,
+        Action::TurnUpRight,
+        Action::TurnDownLeft,
+        Action::TurnDownRight,
+        Action::Pause,
+        Action::Restart,
+        Action::Quit,
+        Action::Mute,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Action::TurnUp => "Up",
+            Action::TurnDown => "Down",
+            Action::TurnLeft => "Left",
+            Action::TurnRight => "Right",
+            Action::TurnUpLeft => "Up-left",
+            Action::TurnUpRight => "Up-right",
+            Action::TurnDownLeft => "Down-left",
+            Action::TurnDownRight => "Down-right",
+            Action::Pause => "Pause",
+            Action::Restart => "Restart",
+            Action::Quit => "Quit",
+            Action::Mute => "Mute",
+        }
+    }
+
+    /// The direction a turn action steers to, or `None` for all other actions.
+    pub fn dir(self) -> Option<Dir> {
+        match self {
+            Action::TurnUp => Some(Dir::Up),
+            Action::TurnDown => Some(Dir::Down),
+            Action::TurnLeft => Some(Dir::Left),
+            Action::TurnRight => Some(Dir::Right),
+            Action::TurnUpLeft => Some(Dir::UpLeft),
+            Action::TurnUpRight => Some(Dir::UpRight),
+            Action::TurnDownLeft => Some(Dir::DownLeft),
+            Action::TurnDownRight => Some(Dir::DownRight),
+            _ => None,
+        }
+    }
+
+    pub fn from_dir(dir: &Dir) -> Option<Action> {
+        match dir {
+            Dir::Up => Some(Action::TurnUp),
+            Dir::Down => Some(Action::TurnDown),
+            Dir::Left => Some(Action::TurnLeft),
+            Dir::Right => Some(Action::TurnRight),
+            Dir::UpLeft => Some(Action::TurnUpLeft),
+            Dir::UpRight => Some(Action::TurnUpRight),
+            Dir::DownLeft => Some(Action::TurnDownLeft),
+            Dir::DownRight => Some(Action::TurnDownRight),
+            Dir::None => None,
+        }
+    }
+}
+
+/// The keyboard key bound to each action. Keys are logical keys, so letter bindings follow the
+/// keyboard layout.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(from = "HashMap<Action, Key>")]
+pub struct KeyBindings(HashMap<Action, Key>);
+
+impl From<HashMap<Action, Key>> for KeyBindings {
+    /// Binds actions missing from saved bindings, e.g. ones added since they were saved, to the
+    /// keys of the preset the saved bindings came from.
+    fn from(mut keys: HashMap<Action, Key>) -> Self {
+        let preset = Preset::ALL
+            .into_iter()
+            .find(|p| {
+                keys.iter()
+                    .all(|(action, key)| p.bindings().key(*action) == Some(key))
+            })
+            .unwrap_or(Preset::Arrows);
+        for (action, key) in preset.bindings().0 {
+            keys.entry(action).or_insert(key);
+        }
+        KeyBindings(keys)
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Preset::Arrows.bindings()
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> Option<&Key> {
+        self.0.get(&action)
+    }
+
+    /// Binds `action` to `key`. An action that was bound to `key` before gets the key `action` had
+    /// instead, so that no key triggers two actions.
+    pub fn bind(&mut self, action: Action, key: Key) {
+        let previous = self.0.insert(action, key.clone());
+        let Some(other) = self
+            .0
+            .iter()
+            .find(|&(&other, bound)| other != action && *bound == key)
+            .map(|(&other, _)| other)
+        else {
+            return;
+        };
+        match previous {
+            Some(previous) => self.0.insert(other, previous),
+            None => self.0.remove(&other),
+        };
+    }
+
+    /// The preset these bindings are equal to, if any.
+    pub fn preset(&self) -> Option<Preset> {
+        Preset::ALL.into_iter().find(|p| p.bindings() == *self)
+    }
+}
+
+/// Returns a short human-readable name for a key.
+pub fn key_name(key: &Key) -> String {
+    match key {
+        Key::Character(c) => c.to_uppercase(),
+        Key::Space => "Space".to_owned(),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Predefined sets of key bindings.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Preset {
+    Arrows,
+    Wasd,
+    Zqsd,
+    Vim,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Arrows, Preset::Wasd, Preset::Zqsd, Preset::Vim];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Arrows => "Arrows",
+            Preset::Wasd => "WASD",
+            Preset::Zqsd => "ZQSD",
+            Preset::Vim => "Vim (hjkl)",
+        }
+    }
+
+    pub fn bindings(self) -> KeyBindings {
+        let char = |c: &str| Key::Character(c.into());
+        let [up, down, left, right] = match self {
+            Preset::Arrows => [
+                Key::ArrowUp,
+                Key::ArrowDown,
+                Key::ArrowLeft,
+                Key::ArrowRight,
+            ],
+            Preset::Wasd => [char("w"), char("s"), char("a"), char("d")],
+            Preset::Zqsd => [char("z"), char("s"), char("q"), char("d")],
+            Preset::Vim => [char("k"), char("j"), char("h"), char("l")],
+        };
+        // The diagonals sit around the other keys, where the keyboard layout allows.
+        let [up_left, up_right, down_left, down_right] = match self {
+            Preset::Arrows => [Key::Home, Key::PageUp, Key::End, Key::PageDown],
+            Preset::Wasd => [char("q"), char("e"), char("z"), char("c")],
+            Preset::Zqsd => [char("a"), char("e"), char("w"), char("c")],
+            Preset::Vim => [char("y"), char("u"), char("b"), char("n")],
+        };
+        KeyBindings(HashMap::from([
+            (Action::TurnUp, up),
+            (Action::TurnDown, down),
+            (Action::TurnLeft, left),
+            (Action::TurnRight, right),
+            (Action::TurnUpLeft, up_left),
+            (Action::TurnUpRight, up_right),
+            (Action::TurnDownLeft, down_left),
+            (Action::TurnDownRight, down_right),
+            (Action::Pause, char("p")),
+            (Action::Restart, Key::Enter),
+            (Action::Quit, Key::Escape),
+            (Action::Mute, char("m")),
+        ]))
+    }
+}
+
+/// Returns the actions bound to the gamepad buttons and the left stick that were pressed this frame.
+fn gamepad_actions(gamepad: &Gamepad, stick: &GamepadStick) -> Vec<Action> {
+    let mut actions: Vec<Action> = gamepad::just_pressed_dirs(gamepad, stick)
+        .iter()
+        .filter_map(Action::from_dir)
+        .collect();
+    if gamepad.just_pressed(GamepadButton::Start) {
+        actions.push(Action::Pause);
+    }
+    if gamepad.just_pressed(GamepadButton::South) {
+        actions.push(Action::Restart);
+    }
+    actions
+}
+
+/// The actions triggered this frame, collected from all input devices.
+#[derive(Default, Resource)]
+pub struct Actions {
+    /// Actions from the keyboard and the touch screen, which every snake can be steered with.
+    shared: Vec<Action>,
+    /// Actions from each gamepad.
+    gamepads: HashMap<Entity, Vec<Action>>,
+}
+
+impl Actions {
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.iter().any(|a| a == action)
+    }
+
+    /// All actions triggered this frame by any device.
+    pub fn iter(&self) -> impl Iterator<Item = Action> + '_ {
+        self.shared
+            .iter()
+            .chain(self.gamepads.values().flatten())
+            .copied()
+    }
+
+    /// The actions for a snake steered with the keyboard, the touch screen and `gamepad`.
+    pub fn for_player(&self, gamepad: Option<Entity>) -> impl Iterator<Item = Action> + '_ {
+        let from_gamepad = gamepad.and_then(|pad| self.gamepads.get(&pad));
+        self.shared
+            .iter()
+            .chain(from_gamepad.into_iter().flatten())
+            .copied()
+    }
+}
+
+/// If set, the next key pressed is bound to this action instead of triggering anything.
+#[derive(Default, Resource)]
+pub struct Rebinding(pub Option<Action>);
+
+fn collect_actions(
+    keys: Res<ButtonInput<Key>>,
+    gamepads: Query<(Entity, &Gamepad, &GamepadStick)>,
+    mut touch: MessageReader<TouchInput>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+    mut actions: ResMut<Actions>,
+) {
+    actions.shared.clear();
+    actions.gamepads.clear();
+    if let Some(action) = rebinding.0 {
+        if let Some(key) = keys.get_just_pressed().next() {
+            settings.key_bindings.bind(action, key.clone());
+            rebinding.0 = None;
+        }
+        return;
+    }
+    for action in Action::ALL {
+        if settings
+            .key_bindings
+            .key(action)
+            .is_some_and(|key| keys.just_pressed(key.clone()))
+        {
+            actions.shared.push(action);
+        }
+    }
+    for input in touch.read() {
+        match input {
+            TouchInput::Steer(dir) => actions.shared.extend(Action::from_dir(dir)),
+            TouchInput::Tap => actions.shared.push(Action::Restart),
+        }
+    }
+    for (entity, gamepad, stick) in gamepads.iter() {
+        actions
+            .gamepads
+            .insert(entity, gamepad_actions(gamepad, stick));
+    }
+}
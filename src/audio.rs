@@ -0,0 +1,111 @@
+use crate::actions::{Action, Actions};
+use crate::config::GameState;
+use crate::player::{PlayerDied, PlayerTurned};
+use crate::powerups::PowerUpCollected;
+use crate::score::{LevelUp, ScoreUpdate};
+use crate::settings::{MAX_VOLUME, MenuNavigated, Settings};
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, init)
+            .add_systems(OnEnter(GameState::InGame), start_music)
+            .add_systems(
+                Update,
+                (
+                    toggle_mute,
+                    update_music_volume.after(toggle_mute),
+                    play_on_if::<ScoreUpdate>(
+                        |s| &s.eat,
+                        |update| matches!(update, ScoreUpdate::AteFood { .. }),
+                    ),
+                    play_on::<PlayerDied>(|s| &s.death),
+                    play_on::<PlayerTurned>(|s| &s.turn),
+                    play_on::<LevelUp>(|s| &s.level_up),
+                    play_on::<PowerUpCollected>(|s| &s.level_up),
+                    play_on::<MenuNavigated>(|s| &s.menu),
+                ),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct Sounds {
+    eat: Handle<AudioSource>,
+    death: Handle<AudioSource>,
+    turn: Handle<AudioSource>,
+    level_up: Handle<AudioSource>,
+    menu: Handle<AudioSource>,
+    music: Handle<AudioSource>,
+}
+
+/// Marker for the entity playing the background music.
+#[derive(Component)]
+struct Music;
+
+fn init(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(Sounds {
+        eat: asset_server.load("sounds/eat.wav"),
+        death: asset_server.load("sounds/death.wav"),
+        turn: asset_server.load("sounds/turn.wav"),
+        level_up: asset_server.load("sounds/level_up.wav"),
+        menu: asset_server.load("sounds/menu.wav"),
+        music: asset_server.load("sounds/music.wav"),
+    });
+}
+
+fn volume(level: u8, settings: &Settings) -> Volume {
+    if settings.muted {
+        Volume::SILENT
+    } else {
+        Volume::Linear(level as f32 / MAX_VOLUME as f32)
+    }
+}
+
+fn start_music(sounds: Res<Sounds>, settings: Res<Settings>, mut commands: Commands) {
+    commands.spawn((
+        AudioPlayer::new(sounds.music.clone()),
+        PlaybackSettings::LOOP.with_volume(volume(settings.music_volume, &settings)),
+        Music,
+    ));
+}
+
+fn update_music_volume(settings: Res<Settings>, mut music: Query<&mut AudioSink, With<Music>>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut sink in music.iter_mut() {
+        sink.set_volume(volume(settings.music_volume, &settings));
+    }
+}
+
+fn toggle_mute(actions: Res<Actions>, mut settings: ResMut<Settings>) {
+    if actions.just_pressed(Action::Mute) {
+        settings.muted = !settings.muted;
+    }
+}
+
+/// Returns a system that plays a sound for every message of type `M`.
+fn play_on<M: Message>(
+    sound: fn(&Sounds) -> &Handle<AudioSource>,
+) -> impl FnMut(Res<Sounds>, Res<Settings>, MessageReader<M>, Commands) {
+    play_on_if(sound, |_| true)
+}
+
+/// Returns a system that plays a sound for every message of type `M` that `condition` accepts.
+fn play_on_if<M: Message>(
+    sound: fn(&Sounds) -> &Handle<AudioSource>,
+    condition: fn(&M) -> bool,
+) -> impl FnMut(Res<Sounds>, Res<Settings>, MessageReader<M>, Commands) {
+    move |sounds, settings, mut messages, mut commands| {
+        for _ in messages.read().filter(|message| condition(message)) {
+            commands.spawn((
+                AudioPlayer::new(sound(&sounds).clone()),
+                PlaybackSettings::DESPAWN.with_volume(volume(settings.sound_volume, &settings)),
+            ));
+        }
+    }
+}
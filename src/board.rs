@@ -0,0 +1,289 @@
+use crate::config::{Config, Dir, GameState, Position, Topology};
+use crate::settings::{Settings, WorldMode};
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashMap;
+
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), init);
+    }
+}
+
+/// What occupies a cell of the board.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Cell {
+    #[default]
+    Empty,
+    /// The head of a snake.
+    Head(Entity),
+    /// A segment of a snake's tail.
+    Tail(Entity),
+    Food(Entity),
+    /// One end of a pair of portals. Snakes never stop in it, they come out next to its partner.
+    Portal(Entity),
+    /// A moving wall, active spikes or an enemy.
+    Hazard(Entity),
+    /// A power-up waiting to be picked up.
+    PowerUp(Entity),
+    /// A cell that can never be entered. Everything outside a bounded board is a wall.
+    Wall,
+}
+
+/// Marker for the walls inside the board, which the wall around a bounded board doesn't need.
+#[derive(Component)]
+pub struct Obstacle;
+
+impl Cell {
+    /// The entity in this cell, if any.
+    pub fn entity(self) -> Option<Entity> {
+        match self {
+            Cell::Head(entity)
+            | Cell::Tail(entity)
+            | Cell::Food(entity)
+            | Cell::Portal(entity)
+            | Cell::Hazard(entity)
+            | Cell::PowerUp(entity) => Some(entity),
+            Cell::Empty | Cell::Wall => None,
+        }
+    }
+}
+
+/// Marks a slot in [`Chunk::free_slot`] of a cell that is not free.
+const NOT_FREE: u32 = u32::MAX;
+
+/// The cells of one rectangular part of the board.
+struct Chunk {
+    cells: Vec<Cell>,
+    /// Indices of all empty cells, in no particular order.
+    free: Vec<u32>,
+    /// Where each empty cell is in `free`, or [`NOT_FREE`].
+    free_slot: Vec<u32>,
+}
+
+impl Chunk {
+    fn new(size: IVec2) -> Chunk {
+        let count = (size.x * size.y) as u32;
+        Chunk {
+            cells: vec![Cell::Empty; count as usize],
+            free: (0..count).collect(),
+            free_slot: (0..count).collect(),
+        }
+    }
+
+    fn set(&mut self, i: usize, cell: Cell) {
+        self.cells[i] = cell;
+        let slot = self.free_slot[i];
+        if cell == Cell::Empty && slot == NOT_FREE {
+            self.free_slot[i] = self.free.len() as u32;
+            self.free.push(i as u32);
+        } else if cell != Cell::Empty && slot != NOT_FREE {
+            self.free.swap_remove(slot as usize);
+            if let Some(&moved) = self.free.get(slot as usize) {
+                self.free_slot[moved as usize] = slot;
+            }
+            self.free_slot[i] = NOT_FREE;
+        }
+    }
+}
+
+/// What occupies each cell of the board, kept up to date by the systems that move and spawn
+/// things so that lookups and finding a free cell don't have to scan every [`Position`].
+///
+/// The board is made of chunks of [`Config::grid_size_x`] by [`Config::grid_size_y`] cells. A
+/// bounded board is the single chunk at `(0, 0)` surrounded by walls, while an infinite board
+/// has chunks added and removed as the snake explores.
+#[derive(Resource)]
+pub struct Board {
+    chunk_size: IVec2,
+    topology: Topology,
+    bounded: bool,
+    /// Whether moving off one edge of a bounded board leads onto the opposite edge.
+    wrapping: bool,
+    chunks: HashMap<IVec2, Chunk>,
+    /// The partner of each portal.
+    portals: HashMap<Position, Position>,
+}
+
+impl Board {
+    pub fn bounded(width: i32, height: i32, topology: Topology) -> Board {
+        let chunk_size = IVec2::new(width, height);
+        Board {
+            chunk_size,
+            topology,
+            bounded: true,
+            wrapping: false,
+            chunks: HashMap::from([(IVec2::ZERO, Chunk::new(chunk_size))]),
+            portals: HashMap::new(),
+        }
+    }
+
+    pub fn infinite(chunk_size: IVec2, topology: Topology) -> Board {
+        Board {
+            chunk_size,
+            topology,
+            bounded: false,
+            wrapping: false,
+            chunks: HashMap::new(),
+            portals: HashMap::new(),
+        }
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.bounded
+    }
+
+    pub fn chunk_size(&self) -> IVec2 {
+        self.chunk_size
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Lets snakes leave a bounded board on one edge to come back on the opposite one, instead of
+    /// running into the wall. On a hex grid, this needs an even number of rows.
+    pub fn wrap_edges(&mut self) {
+        self.wrapping = self.bounded;
+    }
+
+    /// The cell a snake moving from `pos` in direction `dir` gets to. Moving into a portal leads
+    /// to the cell past its partner.
+    pub fn neighbor(&self, pos: &Position, dir: &Dir) -> Position {
+        let next = self.wrap(pos.neighbor(dir, self.topology));
+        match self.portals.get(&next) {
+            Some(partner) => self.wrap(partner.neighbor(dir, self.topology)),
+            None => next,
+        }
+    }
+
+    /// Moves `pos` back onto the board if it is off an edge that wraps around.
+    fn wrap(&self, pos: Position) -> Position {
+        if !self.wrapping {
+            return pos;
+        }
+        Position {
+            x: pos.x.rem_euclid(self.chunk_size.x),
+            y: pos.y.rem_euclid(self.chunk_size.y),
+        }
+    }
+
+    /// The direction a snake moves in to get from `pos` to `other`, or `None` if it can't in one
+    /// move.
+    pub fn direction(&self, pos: &Position, other: &Position) -> Option<Dir> {
+        self.topology
+            .dirs()
+            .iter()
+            .find(|dir| self.neighbor(pos, dir) == *other)
+            .cloned()
+    }
+
+    /// Whether there is a portal at `pos`.
+    pub fn is_portal(&self, pos: &Position) -> bool {
+        self.portals.contains_key(pos)
+    }
+
+    /// Connects the portals `a` at `a_pos` and `b` at `b_pos` with each other.
+    pub fn link_portals(&mut self, a_pos: &Position, a: Entity, b_pos: &Position, b: Entity) {
+        self.set(a_pos, Cell::Portal(a));
+        self.set(b_pos, Cell::Portal(b));
+        self.portals.insert(a_pos.clone(), b_pos.clone());
+        self.portals.insert(b_pos.clone(), a_pos.clone());
+    }
+
+    /// The chunk `pos` is in.
+    pub fn chunk_of(&self, pos: &Position) -> IVec2 {
+        IVec2::new(pos.x, pos.y).div_euclid(self.chunk_size)
+    }
+
+    /// Whether `pos` is on the board rather than in the wall around it.
+    pub fn contains(&self, pos: &Position) -> bool {
+        !self.bounded || self.chunk_of(pos) == IVec2::ZERO
+    }
+
+    /// The chunk `pos` is in and its index there.
+    fn locate(&self, pos: &Position) -> (IVec2, usize) {
+        let chunk = self.chunk_of(pos);
+        let local = IVec2::new(pos.x, pos.y) - chunk * self.chunk_size;
+        (chunk, (local.y * self.chunk_size.x + local.x) as usize)
+    }
+
+    /// What is at `pos`. Cells in chunks that are not loaded are empty.
+    pub fn at(&self, pos: &Position) -> Cell {
+        if !self.contains(pos) {
+            return Cell::Wall;
+        }
+        let (chunk, i) = self.locate(pos);
+        self.chunks
+            .get(&chunk)
+            .map_or(Cell::Empty, |chunk| chunk.cells[i])
+    }
+
+    /// Puts `cell` at `pos`, replacing whatever was there before.
+    pub fn set(&mut self, pos: &Position, cell: Cell) {
+        if !self.contains(pos) {
+            return;
+        }
+        let (chunk, i) = self.locate(pos);
+        let chunk_size = self.chunk_size;
+        self.chunks
+            .entry(chunk)
+            .or_insert_with(|| Chunk::new(chunk_size))
+            .set(i, cell);
+    }
+
+    /// Records that `entity` left `pos`. Does nothing if something else took over the cell since.
+    pub fn vacate(&mut self, pos: &Position, entity: Entity) {
+        if self.at(pos).entity() == Some(entity) {
+            self.set(pos, Cell::Empty);
+        }
+    }
+
+    /// Forgets everything in `chunk`.
+    pub fn unload(&mut self, chunk: IVec2) {
+        self.chunks.remove(&chunk);
+        let chunk_size = self.chunk_size;
+        self.portals.retain(|pos, partner| {
+            let chunk_of = |pos: &Position| IVec2::new(pos.x, pos.y).div_euclid(chunk_size);
+            chunk_of(pos) != chunk && chunk_of(partner) != chunk
+        });
+    }
+
+    /// Whether any part of a snake is in `chunk`.
+    pub fn has_snake(&self, chunk: IVec2) -> bool {
+        self.chunks.get(&chunk).is_some_and(|chunk| {
+            chunk
+                .cells
+                .iter()
+                .any(|cell| matches!(cell, Cell::Head(_) | Cell::Tail(_)))
+        })
+    }
+
+    /// A uniformly chosen empty cell of `chunk`, or `None` if it is full.
+    pub fn random_free(&self, chunk: IVec2, rng: &mut impl Rng) -> Option<Position> {
+        let free = &self.chunks.get(&chunk)?.free;
+        if free.is_empty() {
+            return None;
+        }
+        let i = free[rng.random_range(0..free.len())] as i32;
+        Some(Position {
+            x: chunk.x * self.chunk_size.x + i % self.chunk_size.x,
+            y: chunk.y * self.chunk_size.y + i / self.chunk_size.x,
+        })
+    }
+}
+
+pub fn init(config: Res<Config>, settings: Res<Settings>, mut commands: Commands) {
+    let board = match settings.world() {
+        WorldMode::Bounded => {
+            Board::bounded(config.grid_size_x, config.grid_size_y, config.topology)
+        }
+        WorldMode::Infinite => Board::infinite(
+            IVec2::new(config.grid_size_x, config.grid_size_y),
+            config.topology,
+        ),
+    };
+    commands.insert_resource(board);
+}
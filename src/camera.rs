@@ -1,36 +1,105 @@
 use crate::config::Config;
+use crate::player::Player;
+use crate::settings::{Settings, WorldMode};
+use bevy::input::mouse::AccumulatedMouseScroll;
 use bevy::{prelude::*, window::PrimaryWindow};
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, init)
-            .add_systems(Update, fit_to_window);
+        app.init_resource::<CameraShake>()
+            .add_systems(Startup, init)
+            .add_systems(Update, (zoom_with_mouse_wheel, update_camera).chain());
     }
 }
 
+/// Offset in world pixels added to the camera position, used to shake the screen.
+#[derive(Default, Resource)]
+pub struct CameraShake(pub Vec2);
+
+/// Where the camera looks without the shake while following the head.
+#[derive(Component, Default)]
+pub struct Followed(Option<Vec2>);
+
+/// Empty space around the board in world pixels.
+const BOARD_MARGIN: f32 = 25.0;
+
+/// How quickly the camera catches up with the head in [`CameraMode::FollowHead`]. Higher is faster.
+const FOLLOW_SPEED: f32 = 5.0;
+
+/// The center of the board in world pixels.
+fn board_center(config: &Config) -> Vec2 {
+    config.board_rect().center()
+}
+
+/// The size of the board including its margin in world pixels.
+fn board_size(config: &Config) -> Vec2 {
+    config.board_rect().size() + 2.0 * BOARD_MARGIN
+}
+
 fn init(config: Res<Config>, mut commands: Commands) {
     commands.spawn((
         Camera2d,
-        Transform::from_xyz(
-            ((config.grid_size_x - 1) * config.pixels_per_cell) as f32 / 2.0,
-            (config.grid_size_y * config.pixels_per_cell) as f32 / 2.0,
-            0.0,
-        ),
+        Transform::from_translation(board_center(&config).extend(0.0)),
+        Followed::default(),
     ));
 }
 
-fn fit_to_window(
+fn zoom_with_mouse_wheel(scroll: Res<AccumulatedMouseScroll>, mut settings: ResMut<Settings>) {
+    if !settings.camera_follows_head() || scroll.delta.y == 0.0 {
+        return;
+    }
+    settings.change_view_cells(-scroll.delta.y.signum() as i32);
+}
+
+/// Clamps `target` so that a view of `view` size centered on it stays on the board, or centers
+/// the view on the board along axes where the view is larger.
+fn clamp_to_board(target: Vec2, view: Vec2, config: &Config) -> Vec2 {
+    let center = board_center(config);
+    let spare = ((board_size(config) - view) / 2.0).max(Vec2::ZERO);
+    target.clamp(center - spare, center + spare)
+}
+
+pub fn update_camera(
     config: Res<Config>,
+    settings: Res<Settings>,
+    shake: Res<CameraShake>,
+    time: Res<Time<Real>>,
     window: Query<&Window, With<PrimaryWindow>>,
-    mut query: Query<&mut Transform, With<Camera2d>>,
+    head: Query<&Transform, (With<Player>, Without<Camera2d>)>,
+    mut camera: Query<(&mut Transform, &mut Followed), With<Camera2d>>,
 ) {
     let window = window.single().unwrap();
-    let pixels_x = (config.grid_size_x * config.pixels_per_cell) as f32 + 50.0;
-    let pixels_y = (config.grid_size_y * config.pixels_per_cell) as f32 + 50.0;
-    let min_size = window.width().min(window.height());
-    let mut transform = query.single_mut().unwrap();
-    transform.scale.x = pixels_x / min_size;
-    transform.scale.y = pixels_y / min_size;
+    let window_size = window.size();
+    let board = board_size(&config);
+    let (mut transform, mut followed) = camera.single_mut().unwrap();
+    let (scale, center) = match (settings.camera_follows_head(), head.single()) {
+        (true, Ok(head)) => {
+            let scale = (settings.view_cells as f32 * config.pixels_per_cell as f32)
+                / window_size.min_element();
+            let view = window_size * scale;
+            let head = head.translation.truncate();
+            let smoothing = 1.0 - (-FOLLOW_SPEED * time.delta_secs()).exp();
+            let target = followed
+                .0
+                .map_or(head, |current| current.lerp(head, smoothing));
+            let center = match settings.world() {
+                WorldMode::Bounded => clamp_to_board(target, view, &config),
+                WorldMode::Infinite => target,
+            };
+            followed.0 = Some(center);
+            (scale, center)
+        }
+        _ => {
+            followed.0 = None;
+            // Scale uniformly so that the whole board fits and keeps its aspect ratio.
+            let scale = (board / window_size).max_element();
+            // In portrait orientation, move the board to the top to leave room for the touch buttons.
+            let spare_y = (window_size.y * scale - board.y).max(0.0);
+            (scale, board_center(&config) - Vec2::new(0.0, spare_y / 2.0))
+        }
+    };
+    transform.scale = Vec3::new(scale, scale, 1.0);
+    transform.translation = (center + shake.0).extend(transform.translation.z);
 }
@@ -0,0 +1,411 @@
+//! A sequence of hand-built levels, each with an objective to complete before moving on to the
+//! next one.
+
+use crate::board::{self, Board};
+use crate::config::{Config, GameState};
+use crate::level;
+use crate::player::{self, DeathCause, PlayerDied, SnakeSystems, TailSegment};
+use crate::score::ScoreUpdate;
+use crate::settings::{GameMode, Settings};
+use crate::storage;
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<CampaignProgress>(PROGRESS_KEY).unwrap_or_default())
+            .add_systems(
+                OnEnter(GameState::InGame),
+                load_level.after(board::init).before(player::init),
+            )
+            .add_systems(
+                Update,
+                (
+                    check_time_limit.in_set(SnakeSystems::Collide),
+                    track_progress.after(SnakeSystems::Collide),
+                    check_objective.after(track_progress),
+                    update_hud.after(track_progress),
+                )
+                    .run_if(in_state(GameState::InGame).and(resource_exists::<LevelRun>)),
+            )
+            .add_systems(Update, (keep_level_unlocked, save_progress));
+    }
+}
+
+const PROGRESS_KEY: &str = "campaign";
+
+/// What has to be done to complete a level.
+#[derive(Clone, Copy)]
+pub enum Objective {
+    EatFood(u32),
+    /// Grow the snake to this many cells, including its head.
+    ReachLength(usize),
+}
+
+impl Objective {
+    fn is_met(self, food_eaten: u32, length: usize) -> bool {
+        match self {
+            Objective::EatFood(food) => food_eaten >= food,
+            Objective::ReachLength(target) => length >= target,
+        }
+    }
+
+    fn progress(self, food_eaten: u32, length: usize) -> String {
+        match self {
+            Objective::EatFood(food) => format!("Food: {}/{food}", food_eaten.min(food)),
+            Objective::ReachLength(target) => format!("Length: {}/{target}", length.min(target)),
+        }
+    }
+}
+
+pub struct Level {
+    pub name: &'static str,
+    /// The rows of the board from top to bottom, in the format described in [`crate::level`].
+    map: &'static [&'static str],
+    objective: Objective,
+    /// Seconds until the snake runs out of time, if there is a limit.
+    time_limit: Option<u32>,
+    /// Completing the level within this many seconds earns three stars, within twice as many two.
+    par_time: u32,
+}
+
+impl Level {
+    /// Width and height of the board in cells.
+    pub fn size(&self) -> IVec2 {
+        level::size(self.map)
+    }
+
+    fn stars(&self, time: Duration) -> u8 {
+        let par = Duration::from_secs(self.par_time as u64);
+        if time <= par {
+            3
+        } else if time <= par * 2 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+pub const LEVELS: &[Level] = &[
+    Level {
+        name: "First bites",
+        map: &[
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+            "...>................",
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+            "....................",
+        ],
+        objective: Objective::EatFood(5),
+        time_limit: None,
+        par_time: 20,
+    },
+    Level {
+        name: "Pillars",
+        map: &[
+            "....................",
+            "....................",
+            "...##..........##...",
+            "...##..........##...",
+            "....................",
+            "....................",
+            "....................",
+            "...>.....##.........",
+            ".........##.........",
+            "....................",
+            "....................",
+            "...##..........##...",
+            "...##..........##...",
+            "....................",
+            "....................",
+        ],
+        objective: Objective::EatFood(10),
+        time_limit: None,
+        par_time: 45,
+    },
+    Level {
+        name: "Corridors",
+        map: &[
+            "....................",
+            "....................",
+            "....................",
+            "..##############....",
+            "....................",
+            "....................",
+            "....................",
+            "....##############..",
+            "....................",
+            "..>.................",
+            "....................",
+            "..##############....",
+            "....................",
+            "....................",
+            "....................",
+        ],
+        objective: Objective::ReachLength(25),
+        time_limit: None,
+        par_time: 60,
+    },
+    Level {
+        name: "Against the clock",
+        map: &[
+            "....................",
+            "....................",
+            "....................",
+            ".........##.........",
+            ".........##.........",
+            ".........##.........",
+            "....................",
+            "...######..######...",
+            "....................",
+            ".........##.........",
+            ".........##.........",
+            "..>......##.........",
+            "....................",
+            "....................",
+            "....................",
+        ],
+        objective: Objective::EatFood(12),
+        time_limit: Some(90),
+        par_time: 50,
+    },
+    Level {
+        name: "Maze",
+        map: &[
+            "....................",
+            ".######.....######..",
+            ".#................#.",
+            ".#..######..####..#.",
+            ".#..#..........#..#.",
+            ".#..#..........#..#.",
+            "....#...####...#....",
+            "........#..#........",
+            "....#...#..#...#....",
+            ".#..#..........#..#.",
+            ".#..#..........#..#.",
+            ".#..######..####..#.",
+            ".#..>.............#.",
+            ".######.....######..",
+            "....................",
+        ],
+        objective: Objective::ReachLength(40),
+        time_limit: Some(180),
+        par_time: 100,
+    },
+];
+
+/// The level at `index`, or the last one if there are fewer levels.
+pub fn level(index: usize) -> &'static Level {
+    &LEVELS[index.min(LEVELS.len() - 1)]
+}
+
+/// The stars earned on the levels so far, stored between sessions.
+#[derive(Resource, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct CampaignProgress {
+    /// The most stars earned on each level, in order. Levels that were never completed have none.
+    stars: Vec<u8>,
+}
+
+impl CampaignProgress {
+    fn stars(&self, level: usize) -> u8 {
+        self.stars.get(level).copied().unwrap_or(0)
+    }
+
+    fn record(&mut self, level: usize, stars: u8) {
+        if self.stars.len() <= level {
+            self.stars.resize(level + 1, 0);
+        }
+        self.stars[level] = self.stars[level].max(stars);
+    }
+
+    /// How many levels can be played: every level up to and including the first one that was
+    /// never completed.
+    fn unlocked(&self) -> usize {
+        (0..LEVELS.len())
+            .position(|level| self.stars(level) == 0)
+            .map_or(LEVELS.len(), |level| level + 1)
+    }
+}
+
+/// The level being played and how far the snake got.
+#[derive(Resource)]
+struct LevelRun {
+    level: usize,
+    food_eaten: u32,
+    time: Duration,
+}
+
+/// Inserted when a level is completed, for the screen shown after it.
+#[derive(Resource)]
+pub struct LevelComplete {
+    pub name: &'static str,
+    pub stars: u8,
+    /// The most stars ever earned on the level, including this time.
+    pub best_stars: u8,
+    pub time: Duration,
+    /// Whether this was the last level of the campaign.
+    pub last: bool,
+}
+
+/// Marker for the text showing the objective and time of the level.
+#[derive(Component)]
+struct LevelHud;
+
+/// Builds the walls of the level and puts the snake at its start, or clears the campaign state
+/// outside of [`GameMode::Campaign`].
+fn load_level(
+    config: Res<Config>,
+    settings: Res<Settings>,
+    theme: Res<ActiveTheme>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.remove_resource::<LevelComplete>();
+    if settings.game_mode != GameMode::Campaign {
+        commands.remove_resource::<LevelRun>();
+        return;
+    }
+    let index = settings.campaign_level.min(LEVELS.len() - 1);
+    level::build_walls(
+        LEVELS[index].map,
+        &config,
+        &theme,
+        &mut board,
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+    );
+    commands.insert_resource(LevelRun {
+        level: index,
+        food_eaten: 0,
+        time: Duration::ZERO,
+    });
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: theme.font.clone(),
+            font_size: 30.0,
+            ..default()
+        },
+        TextColor(theme.text),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(50.0),
+            bottom: Val::Px(5.0),
+            ..default()
+        },
+        LevelHud,
+    ));
+}
+
+fn track_progress(
+    time: Res<Time>,
+    mut updates: MessageReader<ScoreUpdate>,
+    mut run: ResMut<LevelRun>,
+) {
+    run.time += time.delta();
+    for update in updates.read() {
+        if let ScoreUpdate::AteFood { .. } = update {
+            run.food_eaten += 1;
+        }
+    }
+}
+
+fn check_time_limit(run: Res<LevelRun>, mut died: MessageWriter<PlayerDied>) {
+    let Some(limit) = LEVELS[run.level].time_limit else {
+        return;
+    };
+    if run.time >= Duration::from_secs(limit as u64) {
+        died.write(PlayerDied {
+            cause: DeathCause::OutOfTime,
+        });
+    }
+}
+
+/// Ends the level once its objective is met, recording the stars earned and moving the campaign
+/// on to the next level.
+fn check_objective(
+    run: Res<LevelRun>,
+    segments: Query<(), With<TailSegment>>,
+    mut died: MessageReader<PlayerDied>,
+    mut progress: ResMut<CampaignProgress>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    let level = &LEVELS[run.level];
+    // Dying in the same move doesn't count.
+    if died.read().count() > 0 || !level.objective.is_met(run.food_eaten, segments.count() + 1) {
+        return;
+    }
+    let stars = level.stars(run.time);
+    progress.record(run.level, stars);
+    let last = run.level + 1 == LEVELS.len();
+    if !last {
+        settings.campaign_level = run.level + 1;
+    }
+    commands.insert_resource(LevelComplete {
+        name: level.name,
+        stars,
+        best_stars: progress.stars(run.level),
+        time: run.time,
+        last,
+    });
+    next_state.set(GameState::GameOver);
+}
+
+fn update_hud(
+    run: Res<LevelRun>,
+    segments: Query<(), With<TailSegment>>,
+    mut hud: Query<&mut Text, With<LevelHud>>,
+) {
+    let level = &LEVELS[run.level];
+    let mut text = format!(
+        "{}   {}",
+        level.name,
+        level
+            .objective
+            .progress(run.food_eaten, segments.count() + 1)
+    );
+    if let Some(limit) = level.time_limit {
+        let left = Duration::from_secs(limit as u64).saturating_sub(run.time);
+        text += &format!("   Time: {}s", left.as_secs_f32().ceil());
+    }
+    for mut hud in hud.iter_mut() {
+        if hud.0 != text {
+            hud.0.clone_from(&text);
+        }
+    }
+}
+
+/// Keeps the level chosen on the settings screen from going past the levels unlocked so far.
+fn keep_level_unlocked(progress: Res<CampaignProgress>, mut settings: ResMut<Settings>) {
+    let unlocked = progress.unlocked();
+    if settings.campaign_level >= unlocked {
+        settings.campaign_level = unlocked - 1;
+    }
+}
+
+fn save_progress(progress: Res<CampaignProgress>) {
+    if progress.is_changed() && !progress.is_added() {
+        storage::save(PROGRESS_KEY, progress.as_ref());
+    }
+}
@@ -1,58 +1,270 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Resource)]
 pub struct Config {
     pub grid_size_x: i32,
     pub grid_size_y: i32,
     pub pixels_per_cell: i32,
+    /// Interpolate moving entities between cells instead of snapping them once per tick.
+    pub smooth_movement: bool,
+    pub topology: Topology,
 }
 
-pub const BACKGROUND_COLOR: Color = Color::BLACK;
+impl Config {
+    /// The center of the cell at `pos` in world pixels.
+    pub fn cell_center(&self, pos: &Position) -> Vec2 {
+        self.topology.cell_center(pos) * self.pixels_per_cell as f32
+    }
+
+    /// The cell whose center is closest to `point` in world pixels, the inverse of
+    /// [`Config::cell_center`].
+    pub fn cell_at(&self, point: Vec2) -> Position {
+        self.topology.cell_at(point / self.pixels_per_cell as f32)
+    }
+
+    /// The area covered by the cells of a bounded board in world pixels.
+    pub fn board_rect(&self) -> Rect {
+        let size = Vec2::new(self.grid_size_x as f32, self.grid_size_y as f32);
+        let rect = match self.topology {
+            Topology::Square => Rect::from_corners(Vec2::splat(-0.5), size - 0.5),
+            Topology::Hex => {
+                // Odd rows stick out half a cell to the right, and the pointy tops and bottoms
+                // of the first and last rows reach beyond their centers.
+                let odd_rows = if self.grid_size_y > 1 { 0.5 } else { 0.0 };
+                Rect::new(
+                    -0.5,
+                    -HEX_RADIUS,
+                    size.x - 0.5 + odd_rows,
+                    (size.y - 1.0) * HEX_ROW_HEIGHT + HEX_RADIUS,
+                )
+            }
+        };
+        Rect {
+            min: rect.min * self.pixels_per_cell as f32,
+            max: rect.max * self.pixels_per_cell as f32,
+        }
+    }
+}
+
+/// The distance from the center of a hexagonal cell to its corners, in cells.
+pub const HEX_RADIUS: f32 = 0.577_350_26;
+
+/// The vertical distance between the centers of two rows of hexagonal cells, in cells.
+pub const HEX_ROW_HEIGHT: f32 = 0.866_025_4;
+
+/// How the cells of the board are shaped and connected.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Topology {
+    /// Square cells with four neighbours.
+    #[default]
+    Square,
+    /// Hexagonal cells with six neighbours, pointing up and down. Every odd row is shifted half a
+    /// cell to the right so that the board stays rectangular.
+    Hex,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 2] = [Topology::Square, Topology::Hex];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Square => "Square",
+            Topology::Hex => "Hexagonal",
+        }
+    }
+
+    /// The directions a snake can move in, counterclockwise starting from the right.
+    pub fn dirs(self) -> &'static [Dir] {
+        match self {
+            Topology::Square => &[Dir::Right, Dir::Up, Dir::Left, Dir::Down],
+            Topology::Hex => &[
+                Dir::Right,
+                Dir::UpRight,
+                Dir::UpLeft,
+                Dir::Left,
+                Dir::DownLeft,
+                Dir::DownRight,
+            ],
+        }
+    }
+
+    /// The center of the cell at `pos`, in cells.
+    pub fn cell_center(self, pos: &Position) -> Vec2 {
+        match self {
+            Topology::Square => Vec2::new(pos.x as f32, pos.y as f32),
+            Topology::Hex => Vec2::new(
+                pos.x as f32 + 0.5 * pos.y.rem_euclid(2) as f32,
+                pos.y as f32 * HEX_ROW_HEIGHT,
+            ),
+        }
+    }
+
+    /// The cell whose center is closest to `point`, in cells.
+    pub fn cell_at(self, point: Vec2) -> Position {
+        let y = match self {
+            Topology::Square => point.y,
+            Topology::Hex => point.y / HEX_ROW_HEIGHT,
+        }
+        .round() as i32;
+        let row = Position { x: 0, y };
+        let guess = Position {
+            x: (point.x - self.cell_center(&row).x).round() as i32,
+            y,
+        };
+        // On a hex grid, the closest center can be in the row above or below.
+        self.dirs()
+            .iter()
+            .map(|dir| guess.neighbor(dir, self))
+            .chain([guess.clone()])
+            .min_by(|a, b| {
+                let distance = |pos: &Position| self.cell_center(pos).distance_squared(point);
+                distance(a).total_cmp(&distance(b))
+            })
+            .unwrap_or(guess)
+    }
+
+    /// The offset from `pos` to its neighbour in direction `dir`.
+    fn offset(self, pos: &Position, dir: &Dir) -> (i32, i32) {
+        // On odd rows, the cells above and below are half a cell further to the right.
+        let odd = pos.y.rem_euclid(2);
+        match (self, dir) {
+            (_, Dir::None) => (0, 0),
+            (_, Dir::Left) => (-1, 0),
+            (_, Dir::Right) => (1, 0),
+            (Topology::Square, Dir::Up) => (0, 1),
+            (Topology::Square, Dir::Down) => (0, -1),
+            (Topology::Hex, Dir::UpLeft) => (odd - 1, 1),
+            (Topology::Hex, Dir::UpRight) => (odd, 1),
+            (Topology::Hex, Dir::DownLeft) => (odd - 1, -1),
+            (Topology::Hex, Dir::DownRight) => (odd, -1),
+            // Directions that don't exist in this topology don't move.
+            _ => (0, 0),
+        }
+    }
+
+    /// Turns `dir` by `steps` neighbours counterclockwise, or clockwise if `steps` is negative.
+    fn rotated(self, dir: &Dir, steps: i32) -> Dir {
+        let dirs = self.dirs();
+        match dirs.iter().position(|d| d == dir) {
+            Some(i) => dirs[(i as i32 + steps).rem_euclid(dirs.len() as i32) as usize].clone(),
+            None => dir.clone(),
+        }
+    }
+
+    /// The direction after turning counterclockwise to the next neighbour.
+    pub fn rotated_left(self, dir: &Dir) -> Dir {
+        self.rotated(dir, 1)
+    }
+
+    /// The direction after turning clockwise to the next neighbour.
+    pub fn rotated_right(self, dir: &Dir) -> Dir {
+        self.rotated(dir, -1)
+    }
+
+    /// The direction a snake heading in `heading` moves in when steered to `input`, or `None` if
+    /// the input doesn't steer anywhere in this topology. Up and down have no neighbour on a hex
+    /// grid, so they keep going left or right, whichever the snake is heading to.
+    pub fn steer(self, input: &Dir, heading: &Dir) -> Option<Dir> {
+        let rightwards = matches!(heading, Dir::Right | Dir::UpRight | Dir::DownRight);
+        match (self, input) {
+            (_, Dir::None) => None,
+            (Topology::Square, Dir::Left | Dir::Right | Dir::Up | Dir::Down) => Some(input.clone()),
+            (Topology::Square, _) => None,
+            (Topology::Hex, Dir::Up) if rightwards => Some(Dir::UpRight),
+            (Topology::Hex, Dir::Up) => Some(Dir::UpLeft),
+            (Topology::Hex, Dir::Down) if rightwards => Some(Dir::DownRight),
+            (Topology::Hex, Dir::Down) => Some(Dir::DownLeft),
+            (Topology::Hex, _) => Some(input.clone()),
+        }
+    }
+}
 
 #[derive(States, Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub enum GameState {
     #[default]
+    Loading,
     InGame,
     GameOver,
+    Settings,
+    /// Editing the custom level.
+    Editor,
 }
 
-#[derive(Clone, Component, Eq, Hash, PartialEq)]
+#[derive(Clone, Component, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
+/// The cell an entity occupied before the last tick, used to interpolate its movement.
+#[derive(Clone, Component, Eq, PartialEq)]
+pub struct PreviousPosition(pub Position);
+
+/// Fires once per game tick, when the snake moves by one cell.
+#[derive(Resource)]
+pub struct TickTimer(pub Timer);
+
+/// Run condition for systems that only need to run when the snake has just moved.
+pub fn on_tick(timer: Option<Res<TickTimer>>) -> bool {
+    timer.is_some_and(|timer| timer.0.just_finished())
+}
+
 #[derive(Clone, Component, Eq, PartialEq)]
 pub struct ZLayer {
     pub z: i32,
 }
 
 impl Position {
-    pub fn apply_offset(&mut self, dir: &Dir) {
-        let (dx, dy) = dir.to_x_y();
+    pub fn apply_offset(&mut self, dir: &Dir, topology: Topology) {
+        let (dx, dy) = topology.offset(self, dir);
         self.x += dx;
         self.y += dy;
     }
+    /// The neighbouring cell in direction `dir`.
+    pub fn neighbor(&self, dir: &Dir, topology: Topology) -> Position {
+        let mut pos = self.clone();
+        pos.apply_offset(dir, topology);
+        pos
+    }
+    /// The direction of a neighbouring cell, or `None` if `other` is not adjacent.
+    pub fn direction_to(&self, other: &Position, topology: Topology) -> Option<Dir> {
+        topology
+            .dirs()
+            .iter()
+            .find(|dir| self.neighbor(dir, topology) == *other)
+            .cloned()
+    }
 }
 
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Dir {
     None,
     Left,
     Right,
     Up,
     Down,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 impl Dir {
-    pub fn to_x_y(&self) -> (i32, i32) {
-        match self {
-            Dir::None => (0, 0),
-            Dir::Left => (-1, 0),
-            Dir::Right => (1, 0),
-            Dir::Up => (0, 1),
-            Dir::Down => (0, -1),
-        }
+    /// Counterclockwise angle from the positive x axis in radians. The diagonals point to the
+    /// neighbours above and below on a hex grid.
+    pub fn angle(&self) -> f32 {
+        let degrees: f32 = match self {
+            Dir::None | Dir::Right => 0.0,
+            Dir::UpRight => 60.0,
+            Dir::Up => 90.0,
+            Dir::UpLeft => 120.0,
+            Dir::Left => 180.0,
+            Dir::DownLeft => 240.0,
+            Dir::Down => 270.0,
+            Dir::DownRight => 300.0,
+        };
+        degrees.to_radians()
     }
     pub fn opposite(&self) -> Dir {
         match self {
@@ -61,6 +273,10 @@ impl Dir {
             Dir::Right => Dir::Left,
             Dir::Up => Dir::Down,
             Dir::Down => Dir::Up,
+            Dir::UpLeft => Dir::DownRight,
+            Dir::UpRight => Dir::DownLeft,
+            Dir::DownLeft => Dir::UpRight,
+            Dir::DownRight => Dir::UpLeft,
         }
     }
 }
@@ -0,0 +1,383 @@
+//! A challenge that is the same for everyone on the same day: the board, its rules and where the
+//! food appears are all derived from the date. Only the first round of a day counts, and playing
+//! on consecutive days builds up a streak.
+
+use crate::board::{self, Board, Cell, Obstacle};
+use crate::config::{Config, GameState, Position, TickTimer, ZLayer};
+use crate::food::FoodSeed;
+use crate::player::{self, PlayerDied, SnakeStart, SnakeSystems, TailSegment};
+use crate::score::ScoreUpdate;
+use crate::settings::{GameMode, Settings};
+use crate::theme::ActiveTheme;
+use crate::{grid, storage};
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<DailyProgress>(PROGRESS_KEY).unwrap_or_default())
+            .add_systems(
+                OnEnter(GameState::InGame),
+                (
+                    init.before(board::init).before(grid::init),
+                    set_up_board
+                        .after(board::init)
+                        .after(init)
+                        .before(player::init),
+                    set_speed.after(player::init).after(init),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    track_round.after(SnakeSystems::Collide),
+                    record_result.after(track_round),
+                )
+                    .run_if(in_state(GameState::InGame).and(resource_exists::<DailyRun>)),
+            )
+            .add_systems(Update, save_progress);
+    }
+}
+
+const PROGRESS_KEY: &str = "daily";
+
+/// No obstacles are placed this close to where the snake starts.
+const START_CLEARANCE: i32 = 3;
+
+/// Today's date as the number of days since 1970-01-01, in UTC.
+pub fn today() -> i64 {
+    (platform::now_millis() / 86_400_000.0).floor() as i64
+}
+
+/// Formats a number of days since 1970-01-01 as `YYYY-MM-DD`.
+fn format_date(day: i64) -> String {
+    // Howard Hinnant's `civil_from_days`, with years starting on March 1st.
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+/// How the speed of the snake changes during the round.
+#[derive(Clone, Copy)]
+enum SpeedCurve {
+    Steady,
+    /// Faster with every food eaten.
+    Accelerating,
+    /// Fast from the start.
+    Fast,
+}
+
+impl SpeedCurve {
+    const ALL: [SpeedCurve; 3] = [
+        SpeedCurve::Steady,
+        SpeedCurve::Accelerating,
+        SpeedCurve::Fast,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            SpeedCurve::Steady => "steady speed",
+            SpeedCurve::Accelerating => "speeding up",
+            SpeedCurve::Fast => "fast",
+        }
+    }
+
+    /// Seconds per move after eating `food_eaten` food.
+    fn interval(self, food_eaten: u32) -> f32 {
+        match self {
+            SpeedCurve::Steady => 0.2,
+            SpeedCurve::Accelerating => (0.2 * 0.96_f32.powi(food_eaten as i32)).max(0.08),
+            SpeedCurve::Fast => 0.14,
+        }
+    }
+}
+
+/// The rules of one day's challenge.
+pub struct DailyRules {
+    /// Width and height of the board in cells.
+    pub board_size: i32,
+    /// Whether the snake comes back on the opposite edge instead of hitting a wall.
+    wrapping: bool,
+    speed: SpeedCurve,
+    /// How many short walls are placed on the board.
+    obstacles: u32,
+    /// The seed for everything random during the round.
+    seed: u64,
+}
+
+impl DailyRules {
+    fn describe(&self) -> String {
+        format!(
+            "{0}x{0}, {1}, {2}, {3} walls",
+            self.board_size,
+            if self.wrapping {
+                "wrapping edges"
+            } else {
+                "solid edges"
+            },
+            self.speed.name(),
+            self.obstacles,
+        )
+    }
+}
+
+/// The rules of the challenge on `day`, which is counted from 1970-01-01.
+pub fn rules(day: i64) -> DailyRules {
+    let seed = (day as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ 0x5EED_DA11;
+    let mut rng = StdRng::seed_from_u64(seed);
+    DailyRules {
+        board_size: *[16, 20, 24, 30].choose(&mut rng).unwrap(),
+        wrapping: rng.random_bool(0.5),
+        speed: *SpeedCurve::ALL.choose(&mut rng).unwrap(),
+        obstacles: rng.random_range(0..=8),
+        seed,
+    }
+}
+
+/// The days the challenge was played, stored between sessions.
+#[derive(Resource, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct DailyProgress {
+    /// The day of the last scored round.
+    last_day: Option<i64>,
+    /// How many days in a row up to `last_day` had a scored round.
+    streak: u32,
+    best_streak: u32,
+}
+
+/// The round being played.
+#[derive(Resource)]
+struct DailyRun {
+    day: i64,
+    rules: DailyRules,
+    /// Whether this is the day's first round, the one that counts.
+    scored: bool,
+    food_eaten: u32,
+    time: Duration,
+}
+
+/// Inserted when a daily challenge round ends, for the screen shown after it.
+#[derive(Resource)]
+pub struct DailyResult {
+    /// A short summary of the round to share with others.
+    pub summary: String,
+    /// Whether this was the day's scored round rather than practice.
+    pub scored: bool,
+}
+
+/// Marker for the text showing the day's rules.
+#[derive(Component)]
+struct DailyHud;
+
+/// Applies the day's rules and starts the streak if this is the day's first round, or clears the
+/// challenge state outside of [`GameMode::Daily`].
+fn init(
+    settings: Res<Settings>,
+    theme: Res<ActiveTheme>,
+    mut progress: ResMut<DailyProgress>,
+    mut config: ResMut<Config>,
+    mut commands: Commands,
+) {
+    commands.remove_resource::<DailyResult>();
+    if settings.game_mode != GameMode::Daily {
+        commands.remove_resource::<DailyRun>();
+        commands.remove_resource::<FoodSeed>();
+        return;
+    }
+    let day = today();
+    let rules = rules(day);
+    // The board was sized for the day the settings last looked at, which may be over by now.
+    let size = rules.board_size;
+    if config.grid_size_x != size || config.grid_size_y != size {
+        config.grid_size_x = size;
+        config.grid_size_y = size;
+    }
+    commands.insert_resource(FoodSeed(rules.seed));
+
+    let scored = progress.last_day != Some(day);
+    if scored {
+        progress.streak = if progress.last_day == Some(day - 1) {
+            progress.streak + 1
+        } else {
+            1
+        };
+        progress.best_streak = progress.best_streak.max(progress.streak);
+        progress.last_day = Some(day);
+    }
+    commands.spawn((
+        Text::new(format!(
+            "Daily {}{}\n{}",
+            format_date(day),
+            if scored { "" } else { " (practice)" },
+            rules.describe(),
+        )),
+        TextFont {
+            font: theme.font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(theme.text),
+        TextLayout::new_with_justify(Justify::Right),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(50.0),
+            bottom: Val::Px(5.0),
+            ..default()
+        },
+        DailyHud,
+    ));
+    commands.insert_resource(DailyRun {
+        day,
+        rules,
+        scored,
+        food_eaten: 0,
+        time: Duration::ZERO,
+    });
+}
+
+/// Wraps the edges if the day's rules say so and places the day's walls, away from where the snake
+/// starts.
+fn set_up_board(
+    run: Option<Res<DailyRun>>,
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let Some(run) = run else {
+        return;
+    };
+    if run.rules.wrapping {
+        board.wrap_edges();
+    }
+    let start = SnakeStart::default().pos;
+    let wall_mesh = meshes.add(grid::cell_mesh(&config, 1.0));
+    let wall_material = materials.add(theme.wall);
+    let mut rng = StdRng::seed_from_u64(run.rules.seed);
+    let size = run.rules.board_size;
+    for _ in 0..run.rules.obstacles {
+        let first = IVec2::new(rng.random_range(0..size), rng.random_range(0..size));
+        let step = if rng.random_bool(0.5) {
+            IVec2::X
+        } else {
+            IVec2::Y
+        };
+        for i in 0..rng.random_range(2..=5) {
+            let pos = Position {
+                x: first.x + i * step.x,
+                y: first.y + i * step.y,
+            };
+            let near_start = (pos.x - start.x).abs() <= START_CLEARANCE
+                && (pos.y - start.y).abs() <= START_CLEARANCE;
+            if near_start || board.at(&pos) != Cell::Empty {
+                continue;
+            }
+            commands.spawn((
+                Mesh2d(wall_mesh.clone()),
+                MeshMaterial2d(wall_material.clone()),
+                pos.clone(),
+                ZLayer { z: 1 },
+                Obstacle,
+            ));
+            board.set(&pos, Cell::Wall);
+        }
+    }
+}
+
+/// Starts the snake at the speed of the day's rules.
+fn set_speed(run: Option<Res<DailyRun>>, mut timer: ResMut<TickTimer>) {
+    if let Some(run) = run {
+        timer
+            .0
+            .set_duration(Duration::from_secs_f32(run.rules.speed.interval(0)));
+    }
+}
+
+/// Counts the food eaten and speeds the snake up as the day's speed curve says.
+fn track_round(
+    time: Res<Time>,
+    mut updates: MessageReader<ScoreUpdate>,
+    mut run: ResMut<DailyRun>,
+    mut timer: ResMut<TickTimer>,
+) {
+    run.time += time.delta();
+    for update in updates.read() {
+        if let ScoreUpdate::AteFood { .. } = update {
+            let speed = run.rules.speed;
+            let before = speed.interval(run.food_eaten);
+            run.food_eaten += 1;
+            // Scale rather than set the interval, so that slow motion keeps working.
+            let interval = timer
+                .0
+                .duration()
+                .mul_f32(speed.interval(run.food_eaten) / before);
+            timer.0.set_duration(interval);
+        }
+    }
+}
+
+fn record_result(
+    mut died: MessageReader<PlayerDied>,
+    run: Res<DailyRun>,
+    progress: Res<DailyProgress>,
+    segments: Query<(), With<TailSegment>>,
+    mut commands: Commands,
+) {
+    if died.read().count() == 0 {
+        return;
+    }
+    let mut summary = format!(
+        "Snake daily {}: {} food, length {}, {:.0} s",
+        format_date(run.day),
+        run.food_eaten,
+        segments.count() + 1,
+        run.time.as_secs_f32(),
+    );
+    if run.scored {
+        summary += &format!(", streak {}", progress.streak);
+    }
+    commands.insert_resource(DailyResult {
+        summary,
+        scored: run.scored,
+    });
+}
+
+fn save_progress(progress: Res<DailyProgress>) {
+    if progress.is_changed() && !progress.is_added() {
+        storage::save(PROGRESS_KEY, progress.as_ref());
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Milliseconds since 1970-01-01 in UTC.
+    pub fn now_millis() -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_millis() as f64)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    /// Milliseconds since 1970-01-01 in UTC.
+    pub fn now_millis() -> f64 {
+        js_sys::Date::now()
+    }
+}
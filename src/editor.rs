@@ -0,0 +1,333 @@
+//! An editor for the custom level, where walls, portals, food and the start of the snake are
+//! placed with the mouse on the level's grid.
+
+use crate::board::Board;
+use crate::config::{Config, Dir, GameState, Position, Topology, ZLayer};
+use crate::level::{self, CUSTOM_LEVEL_KEY, CustomLevel, LevelFile, Tile};
+use crate::settings::{self, GameMode, Settings};
+use crate::storage;
+use crate::theme::ActiveTheme;
+use crate::{grid, portal};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Editor), open_editor)
+            .add_systems(OnExit(GameState::Editor), restore_board_config)
+            .add_systems(
+                Update,
+                (
+                    read_editor_keys,
+                    paint_cells.after(read_editor_keys),
+                    redraw_level.after(paint_cells),
+                    update_help.after(read_editor_keys),
+                )
+                    .run_if(in_state(GameState::Editor)),
+            );
+    }
+}
+
+/// What a left click puts on the level.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Tool {
+    Wall,
+    Portal,
+    Start,
+    Food,
+}
+
+impl Tool {
+    const ALL: [(KeyCode, Tool); 4] = [
+        (KeyCode::Digit1, Tool::Wall),
+        (KeyCode::Digit2, Tool::Portal),
+        (KeyCode::Digit3, Tool::Start),
+        (KeyCode::Digit4, Tool::Food),
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Wall => "Wall",
+            Tool::Portal => "Portal",
+            Tool::Start => "Start",
+            Tool::Food => "Food",
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Editor {
+    tool: Tool,
+}
+
+#[derive(Resource)]
+struct EditorAssets {
+    wall_mesh: Handle<Mesh>,
+    wall_material: Handle<ColorMaterial>,
+    food_mesh: Handle<Mesh>,
+    food_material: Handle<ColorMaterial>,
+    start_mesh: Handle<Mesh>,
+    start_material: Handle<ColorMaterial>,
+    portal_mesh: Handle<Mesh>,
+    /// One material per portal pair, in the colors the pairs get in the game.
+    portal_materials: Vec<Handle<ColorMaterial>>,
+}
+
+/// Marks the entities showing the tiles of the level.
+#[derive(Component)]
+struct EditorTile;
+
+/// Marker for the text listing the tools and keys.
+#[derive(Component)]
+struct EditorHelp;
+
+fn open_editor(
+    level: Res<CustomLevel>,
+    theme: Res<ActiveTheme>,
+    mut config: ResMut<Config>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let size = level::size(&level.0.rows);
+    config.grid_size_x = size.x;
+    config.grid_size_y = size.y;
+    config.topology = Default::default();
+
+    let cells = IRect::from_corners(IVec2::ZERO, size);
+    commands.spawn((
+        Mesh2d(meshes.add(grid::create_grid_mesh(
+            &config,
+            theme.grid_line_width,
+            cells,
+            BVec2::TRUE,
+        ))),
+        MeshMaterial2d(materials.add(theme.grid)),
+    ));
+
+    let cell = config.pixels_per_cell as f32;
+    let tip = cell * 0.4;
+    commands.insert_resource(EditorAssets {
+        wall_mesh: meshes.add(grid::cell_mesh(&config, 1.0)),
+        wall_material: materials.add(theme.wall),
+        food_mesh: meshes.add(Rectangle::new(cell - 3.0, cell - 3.0)),
+        food_material: materials.add(theme.food),
+        start_mesh: meshes.add(Triangle2d::new(
+            Vec2::new(tip, 0.0),
+            Vec2::new(-tip, tip),
+            Vec2::new(-tip, -tip),
+        )),
+        start_material: materials.add(theme.head_fill),
+        portal_mesh: meshes.add(Annulus::new(cell * 0.27, cell * 0.45)),
+        portal_materials: (0..9)
+            .map(|pair| materials.add(theme.portal_color(pair)))
+            .collect(),
+    });
+    commands.insert_resource(Editor { tool: Tool::Wall });
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: theme.font.clone(),
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(theme.text),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.0),
+            top: Val::Px(5.0),
+            ..default()
+        },
+        EditorHelp,
+    ));
+}
+
+/// Puts the board back to the size and shape of the game mode.
+fn restore_board_config(
+    settings: Res<Settings>,
+    level: Res<CustomLevel>,
+    mut config: ResMut<Config>,
+) {
+    settings::sync_board_config(&settings, &level, &mut config);
+}
+
+fn read_editor_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut level: ResMut<CustomLevel>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (key, tool) in Tool::ALL {
+        if keys.just_pressed(key) {
+            editor.tool = tool;
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyC) {
+        level.0 = LevelFile::default();
+    }
+    if keys.just_pressed(KeyCode::KeyS) {
+        storage::save(CUSTOM_LEVEL_KEY, &level.0);
+    }
+    if keys.just_pressed(KeyCode::Enter) {
+        storage::save(CUSTOM_LEVEL_KEY, &level.0);
+        settings.game_mode = GameMode::Custom;
+        next_state.set(GameState::InGame);
+    } else if keys.just_pressed(KeyCode::Tab) {
+        next_state.set(GameState::Settings);
+    }
+}
+
+/// The number of the portal pair the next portal belongs to: the first pair that is missing one
+/// end, or else the first pair not used yet. `None` if all pairs are used.
+fn next_portal_pair(rows: &[String]) -> Option<u32> {
+    let mut ends = [0; 9];
+    for (_, tile) in level::tiles(rows) {
+        if let Tile::Portal(pair) = tile {
+            ends[pair as usize - 1] += 1;
+        }
+    }
+    let pair = ends
+        .iter()
+        .position(|&count| count == 1)
+        .or_else(|| ends.iter().position(|&count| count == 0))?;
+    Some(pair as u32 + 1)
+}
+
+/// Whether a portal at `pos` would be kept when the level is played. Portals next to the edge of
+/// the level or another portal are left out.
+fn portal_fits(rows: &[String], pos: &Position) -> bool {
+    let size = level::size(rows);
+    let mut board = Board::bounded(size.x, size.y, Topology::Square);
+    for (other, tile) in level::tiles(rows) {
+        if let Tile::Portal(_) = tile {
+            // Only where the portals are matters here, not which ones are partners.
+            board.link_portals(&other, Entity::PLACEHOLDER, &other, Entity::PLACEHOLDER);
+        }
+    }
+    portal::can_place(&board, pos)
+}
+
+/// The cell under the mouse cursor, if it is on the level.
+fn hovered_cell(
+    config: &Config,
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Position> {
+    let cursor = window.cursor_position()?;
+    let point = camera.viewport_to_world_2d(camera_transform, cursor).ok()?;
+    let pos = config.cell_at(point);
+    let inside =
+        (0..config.grid_size_x).contains(&pos.x) && (0..config.grid_size_y).contains(&pos.y);
+    inside.then_some(pos)
+}
+
+/// Places the current tool's tile with the left mouse button and erases with the right one. Walls
+/// and food can be painted by dragging. Clicking the start again turns it.
+fn paint_cells(
+    buttons: Res<ButtonInput<MouseButton>>,
+    config: Res<Config>,
+    editor: Res<Editor>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut level: ResMut<CustomLevel>,
+) {
+    let place = buttons.pressed(MouseButton::Left);
+    let erase = buttons.pressed(MouseButton::Right);
+    if !place && !erase {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (window.single(), camera.single()) else {
+        return;
+    };
+    let Some(pos) = hovered_cell(&config, window, camera, camera_transform) else {
+        return;
+    };
+    let rows = &level.0.rows;
+    let current = level::tile_at(rows, &pos);
+    let clicked = buttons.just_pressed(MouseButton::Left);
+    let tile = match (&current, editor.tool) {
+        // There is always exactly one start, which only the start tool moves.
+        (Tile::Start(dir), Tool::Start) if clicked && !erase => {
+            Tile::Start(config.topology.rotated_left(dir))
+        }
+        (Tile::Start(_), _) => return,
+        _ if erase => Tile::Empty,
+        (_, Tool::Wall) => Tile::Wall,
+        (_, Tool::Food) => Tile::Food,
+        (Tile::Portal(_), Tool::Portal) => return,
+        (_, Tool::Portal) if clicked && portal_fits(rows, &pos) => match next_portal_pair(rows) {
+            Some(pair) => Tile::Portal(pair),
+            None => return,
+        },
+        (_, Tool::Start) if clicked => {
+            let old = level::tiles(rows).find(|(_, tile)| matches!(tile, Tile::Start(_)));
+            if let Some((old, _)) = old {
+                level::set_tile(&mut level.0.rows, &old, &Tile::Empty);
+            }
+            Tile::Start(Dir::Right)
+        }
+        _ => return,
+    };
+    if tile != current {
+        level::set_tile(&mut level.0.rows, &pos, &tile);
+    }
+}
+
+/// Shows the tiles of the level whenever it changes.
+fn redraw_level(
+    level: Res<CustomLevel>,
+    assets: Res<EditorAssets>,
+    tiles: Query<Entity, With<EditorTile>>,
+    mut commands: Commands,
+) {
+    if !level.is_changed() && !assets.is_added() {
+        return;
+    }
+    for entity in tiles.iter() {
+        commands.entity(entity).despawn();
+    }
+    for (pos, tile) in level::tiles(&level.0.rows) {
+        let (mesh, material, rotation) = match tile {
+            Tile::Empty => continue,
+            Tile::Wall => (&assets.wall_mesh, &assets.wall_material, 0.0),
+            Tile::Food => (&assets.food_mesh, &assets.food_material, 0.0),
+            Tile::Portal(pair) => (
+                &assets.portal_mesh,
+                &assets.portal_materials[pair as usize - 1],
+                0.0,
+            ),
+            Tile::Start(dir) => (&assets.start_mesh, &assets.start_material, dir.angle()),
+        };
+        commands.spawn((
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_rotation(Quat::from_rotation_z(rotation)),
+            pos,
+            ZLayer { z: 1 },
+            EditorTile,
+        ));
+    }
+}
+
+fn update_help(editor: Res<Editor>, mut help: Query<&mut Text, With<EditorHelp>>) {
+    if !editor.is_changed() {
+        return;
+    }
+    let tools: Vec<String> = Tool::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, (_, tool))| format!("{}: {}", i + 1, tool.name()))
+        .collect();
+    for mut text in help.iter_mut() {
+        text.0 = format!(
+            "Level editor, placing: {}\n{}\nLeft click: place   Right click: erase\n\
+             Enter: test play   S: save   C: clear   Tab: settings",
+            editor.tool.name(),
+            tools.join("   "),
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::camera::{self, CameraShake};
+use crate::config::{Config, Position};
+use crate::player::{Player, PlayerDied, PlayerGrew, TailSegment};
+use crate::score::ScoreUpdate;
+use crate::settings::Settings;
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EffectsRng(StdRng::seed_from_u64(
+            getrandom::u64().unwrap_or(0),
+        )))
+        .init_resource::<Shake>()
+        .add_systems(
+            Update,
+            (
+                burst_on_food,
+                float_score_text,
+                explode_on_death.run_if(on_message::<PlayerDied>),
+                shake_on_death.run_if(on_message::<PlayerDied>),
+                pulse_on_growth,
+                update_particles,
+                update_floating_text,
+                update_pulses,
+                update_shake.before(camera::update_camera),
+            ),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct EffectsRng(StdRng);
+
+/// A small square flying away from where it was spawned and fading out.
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+/// Text rising from where it was spawned and fading out.
+#[derive(Component)]
+struct FloatingText(Timer);
+
+/// Briefly enlarges a tail segment.
+#[derive(Component)]
+struct Pulse(Timer);
+
+/// How long the screen keeps shaking.
+#[derive(Default, Resource)]
+struct Shake(Timer);
+
+/// The most tail segments that burst into particles when the snake dies.
+const MAX_EXPLODING_SEGMENTS: usize = 500;
+
+/// Drawn above the snake and the food.
+const EFFECTS_Z: f32 = 20.0;
+
+fn spawn_particles(
+    center: Vec2,
+    count: usize,
+    speed: f32,
+    color: Color,
+    rng: &mut StdRng,
+    commands: &mut Commands,
+) {
+    for _ in 0..count {
+        let angle = rng.random_range(0.0..TAU);
+        let speed = speed * rng.random_range(0.5..1.0);
+        commands.spawn((
+            Sprite::from_color(color, Vec2::splat(rng.random_range(3.0..7.0))),
+            Transform::from_translation(center.extend(EFFECTS_Z)),
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                lifetime: Timer::from_seconds(rng.random_range(0.3..0.6), TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn burst_on_food(
+    settings: Res<Settings>,
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut rng: ResMut<EffectsRng>,
+    mut updates: MessageReader<ScoreUpdate>,
+    mut commands: Commands,
+) {
+    for update in updates.read() {
+        let ScoreUpdate::AteFood { position } = update else {
+            continue;
+        };
+        if settings.food_particles {
+            let center = config.cell_center(position);
+            spawn_particles(center, 16, 150.0, theme.food, &mut rng.0, &mut commands);
+        }
+    }
+}
+
+fn float_score_text(
+    settings: Res<Settings>,
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut updates: MessageReader<ScoreUpdate>,
+    mut commands: Commands,
+) {
+    for update in updates.read() {
+        let ScoreUpdate::AteFood { position } = update else {
+            continue;
+        };
+        if settings.score_popups {
+            commands.spawn((
+                Text2d::new(format!("+{}", update.points())),
+                TextFont {
+                    font: theme.font.clone(),
+                    font_size: 24.0,
+                    ..default()
+                },
+                TextColor(theme.highlight),
+                Transform::from_translation(config.cell_center(position).extend(EFFECTS_Z)),
+                FloatingText(Timer::from_seconds(0.8, TimerMode::Once)),
+            ));
+        }
+    }
+}
+
+fn explode_on_death(
+    settings: Res<Settings>,
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut rng: ResMut<EffectsRng>,
+    head: Query<&Position, With<Player>>,
+    segments: Query<&Position, With<TailSegment>>,
+    mut commands: Commands,
+) {
+    if !settings.death_explosion {
+        return;
+    }
+    for pos in head.iter() {
+        let center = config.cell_center(pos);
+        spawn_particles(
+            center,
+            24,
+            200.0,
+            theme.head_fill,
+            &mut rng.0,
+            &mut commands,
+        );
+    }
+    // Keep the number of particles reasonable for very long snakes.
+    let step = (segments.iter().len() / MAX_EXPLODING_SEGMENTS).max(1);
+    for pos in segments.iter().step_by(step) {
+        let center = config.cell_center(pos);
+        spawn_particles(center, 6, 120.0, theme.tail, &mut rng.0, &mut commands);
+    }
+}
+
+fn shake_on_death(settings: Res<Settings>, mut shake: ResMut<Shake>) {
+    if settings.screen_shake {
+        shake.0 = Timer::from_seconds(0.4, TimerMode::Once);
+    }
+}
+
+fn pulse_on_growth(
+    settings: Res<Settings>,
+    mut grew: MessageReader<PlayerGrew>,
+    mut commands: Commands,
+) {
+    for PlayerGrew { segment } in grew.read() {
+        if settings.growth_pulse {
+            commands
+                .entity(*segment)
+                .insert(Pulse(Timer::from_seconds(0.25, TimerMode::Once)));
+        }
+    }
+}
+
+fn update_particles(
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    mut commands: Commands,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        if particle.lifetime.tick(time.delta()).is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_secs()).extend(0.0);
+        sprite
+            .color
+            .set_alpha(particle.lifetime.fraction_remaining());
+    }
+}
+
+fn update_floating_text(
+    time: Res<Time>,
+    mut texts: Query<(Entity, &mut FloatingText, &mut Transform, &mut TextColor)>,
+    mut commands: Commands,
+) {
+    for (entity, mut text, mut transform, mut color) in texts.iter_mut() {
+        if text.0.tick(time.delta()).is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation.y += 40.0 * time.delta_secs();
+        color.0.set_alpha(text.0.fraction_remaining());
+    }
+}
+
+fn update_pulses(
+    time: Res<Time>,
+    mut segments: Query<(Entity, &mut Pulse, &mut Transform)>,
+    mut commands: Commands,
+) {
+    for (entity, mut pulse, mut transform) in segments.iter_mut() {
+        let scale = if pulse.0.tick(time.delta()).is_finished() {
+            commands.entity(entity).remove::<Pulse>();
+            1.0
+        } else {
+            // Grow to 1.4 times the size and back.
+            1.0 + 0.4 * (pulse.0.fraction() * PI).sin()
+        };
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+}
+
+fn update_shake(
+    time: Res<Time>,
+    mut rng: ResMut<EffectsRng>,
+    mut shake: ResMut<Shake>,
+    mut offset: ResMut<CameraShake>,
+) {
+    if shake.0.tick(time.delta()).is_finished() {
+        if offset.0 != Vec2::ZERO {
+            offset.0 = Vec2::ZERO;
+        }
+        return;
+    }
+    let strength = 8.0 * shake.0.fraction_remaining();
+    offset.0 = Vec2::new(
+        rng.0.random_range(-strength..=strength),
+        rng.0.random_range(-strength..=strength),
+    );
+}
@@ -1,8 +1,9 @@
+use crate::board::{Board, Cell};
 use crate::config::{Config, GameState, Position, ZLayer};
-use bevy::color::palettes::css;
+use crate::theme::ActiveTheme;
 use bevy::prelude::*;
 use rand::prelude::*;
-use std::collections::HashSet;
+use rand_chacha::ChaCha12Rng;
 
 pub struct FoodPlugin;
 #[derive(Component)]
@@ -10,43 +11,52 @@ pub struct Food;
 
 impl Plugin for FoodPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::InGame), init)
+        app.add_systems(OnEnter(GameState::InGame), init.after(crate::player::init))
             .add_systems(Update, check_spawn.run_if(in_state(GameState::InGame)));
     }
 }
 
 #[derive(Resource)]
-struct FoodSpawnTimer(Timer);
+pub struct FoodSpawnTimer(pub Timer);
 
 #[derive(Resource, Clone)]
-struct FoodAssets {
+pub struct FoodAssets {
     mesh: Handle<Mesh>,
     material: Handle<ColorMaterial>,
 }
 
+/// Places the food. This is the generator behind [`StdRng`], used directly because it can be
+/// saved along with the round.
 #[derive(Resource)]
-struct FoodRng(rand::rngs::StdRng);
+pub struct FoodRng(pub ChaCha12Rng);
+
+/// The seed for placing food, for rounds that have to play out the same for everyone. Without it,
+/// food is placed differently every round.
+#[derive(Resource)]
+pub struct FoodSeed(pub u64);
 
 fn check_spawn(
     time: Res<Time>,
     mut timer: ResMut<FoodSpawnTimer>,
-    query: Query<&Position>,
-    config: Res<Config>,
+    mut board: ResMut<Board>,
     mut commands: Commands,
     assets: Res<FoodAssets>,
     rng: ResMut<FoodRng>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
-        spawn(query, config, &mut commands, &assets, rng.into_inner());
+    // Food in an infinite world comes with the chunks instead.
+    if timer.0.tick(time.delta()).just_finished() && board.is_bounded() {
+        spawn(&mut board, &mut commands, &assets, rng.into_inner());
     }
 }
 
-fn init(
-    query: Query<&Position>,
+pub fn init(
     config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut board: ResMut<Board>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    seed: Option<Res<FoodSeed>>,
 ) {
     commands.insert_resource(FoodSpawnTimer(Timer::from_seconds(
         3.0,
@@ -57,45 +67,36 @@ fn init(
             config.pixels_per_cell as f32 - 3.0,
             config.pixels_per_cell as f32 - 3.0,
         )),
-        material: materials.add(Color::from(css::SALMON)),
+        material: materials.add(theme.food),
     };
 
-    let mut rng = FoodRng(StdRng::seed_from_u64(getrandom::u64().unwrap_or(0)));
-    spawn(query, config, &mut commands, &assets, &mut rng);
+    let seed = seed.map_or_else(|| getrandom::u64().unwrap_or(0), |seed| seed.0);
+    let mut rng = FoodRng(ChaCha12Rng::seed_from_u64(seed));
+    if board.is_bounded() {
+        spawn(&mut board, &mut commands, &assets, &mut rng);
+    }
 
     commands.insert_resource(assets);
     commands.insert_resource(rng);
 }
 
-fn spawn(
-    query: Query<&Position>,
-    config: Res<Config>,
-    commands: &mut Commands,
-    assets: &FoodAssets,
-    rng: &mut FoodRng,
-) {
-    let mut blocked = HashSet::<Position>::new();
-    for pos in query.iter() {
-        blocked.insert(pos.clone());
-    }
-    let mut candidates = Vec::<Position>::new();
-    for x in 0..config.grid_size_x {
-        for y in 0..config.grid_size_y {
-            let pos = Position { x, y };
-            if !blocked.contains(&pos) {
-                candidates.push(pos);
-            }
-        }
-    }
-    if candidates.is_empty() {
+fn spawn(board: &mut Board, commands: &mut Commands, assets: &FoodAssets, rng: &mut FoodRng) {
+    let Some(spawn_pos) = board.random_free(IVec2::ZERO, &mut rng.0) else {
         panic!("No more space to spawn food!")
-    }
-    let spawn_pos = &candidates[rng.0.random_range(0..candidates.len())];
-    commands.spawn((
-        Mesh2d(assets.mesh.clone()),
-        MeshMaterial2d(assets.material.clone()),
-        Food,
-        spawn_pos.clone(),
-        ZLayer { z: 2 },
-    ));
+    };
+    let food = spawn_at(commands, assets, spawn_pos.clone());
+    board.set(&spawn_pos, Cell::Food(food));
+}
+
+/// Spawns a piece of food at `pos`, without recording it on the [`Board`].
+pub fn spawn_at(commands: &mut Commands, assets: &FoodAssets, pos: Position) -> Entity {
+    commands
+        .spawn((
+            Mesh2d(assets.mesh.clone()),
+            MeshMaterial2d(assets.material.clone()),
+            Food,
+            pos,
+            ZLayer { z: 2 },
+        ))
+        .id()
 }
@@ -1,5 +1,11 @@
-use crate::config::{BACKGROUND_COLOR, GameState};
-use bevy::color::palettes::css;
+use crate::actions::{Action, Actions, key_name};
+use crate::campaign::LevelComplete;
+use crate::config::GameState;
+use crate::daily::DailyResult;
+use crate::player::{DeathCause, DeathReport, Wall};
+use crate::settings::Settings;
+use crate::theme::ActiveTheme;
+use crate::time_attack::TimeAttackResult;
 use bevy::prelude::*;
 
 pub struct GameOverScreenPlugin;
@@ -17,7 +23,102 @@ impl Plugin for GameOverScreenPlugin {
 #[derive(Resource)]
 struct GameOverWaitTimer(Timer);
 
-fn show_game_over_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// Marker for the button that opens the settings, for mouse and touch users.
+#[derive(Component)]
+struct SettingsButton;
+
+fn describe_death(cause: &DeathCause) -> String {
+    match cause {
+        DeathCause::Wall(wall) => {
+            let side = match wall {
+                Wall::Left => "left",
+                Wall::Right => "right",
+                Wall::Top => "top",
+                Wall::Bottom => "bottom",
+            };
+            format!("You hit the {side} wall")
+        }
+        DeathCause::Obstacle => "You crashed into an obstacle".to_owned(),
+        DeathCause::Hazard => "You were caught by a hazard".to_owned(),
+        DeathCause::OutOfTime => "You ran out of time".to_owned(),
+        DeathCause::Tail { segment } => format!("You bit your own tail at segment {segment}"),
+    }
+}
+
+fn describe_time_attack(result: &TimeAttackResult) -> String {
+    let rank = match result.rank {
+        Some(0) => "   New best!".to_owned(),
+        Some(rank) => format!("   New high score #{}", rank + 1),
+        None => String::new(),
+    };
+    let scores: Vec<String> = result.high_scores.iter().map(u32::to_string).collect();
+    format!(
+        "\nFood eaten in time: {}{rank}\nHigh scores: {}",
+        result.food_eaten,
+        scores.join("  ")
+    )
+}
+
+fn show_game_over_screen(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    report: Option<Res<DeathReport>>,
+    completed: Option<Res<LevelComplete>>,
+    time_attack: Option<Res<TimeAttackResult>>,
+    daily: Option<Res<DailyResult>>,
+    settings: Res<Settings>,
+) {
+    let (title, mut summary, next) = match (completed, report) {
+        (Some(completed), _) => (
+            "Level complete!",
+            format!(
+                "\n{}\nStars: {}/3 (best {}/3)   Time: {:.1} s",
+                completed.name,
+                completed.stars,
+                completed.best_stars,
+                completed.time.as_secs_f32(),
+            ),
+            if completed.last {
+                "play it again"
+            } else {
+                "play the next level"
+            },
+        ),
+        (None, Some(report)) => (
+            match report.cause {
+                DeathCause::OutOfTime => "Time's up!",
+                _ => "Game over!",
+            },
+            format!(
+                "\n{}\nLength: {}   Time: {:.1} s   Food eaten: {}",
+                describe_death(&report.cause),
+                report.length,
+                report.time_survived.as_secs_f32(),
+                report.food_eaten,
+            ),
+            "restart",
+        ),
+        (None, None) => ("Game over!", String::new(), "restart"),
+    };
+    if let Some(result) = time_attack {
+        summary += &describe_time_attack(&result);
+    }
+    if let Some(daily) = daily {
+        summary += &if daily.scored {
+            format!("\n\nShare your result:\n{}", daily.summary)
+        } else {
+            format!(
+                "\n\nPractice: {}\nToday's scored round is done, come back tomorrow",
+                daily.summary
+            )
+        };
+    }
+    let restart_key = settings
+        .key_bindings
+        .key(Action::Restart)
+        .map_or_else(String::new, |key| format!("{} or ", key_name(key)));
+    let hint =
+        format!("\nSteer, press {restart_key}tap to {next}\nPress Tab or Select for settings");
     commands.insert_resource(GameOverWaitTimer(Timer::from_seconds(0.2, TimerMode::Once)));
     commands
         .spawn((Node {
@@ -33,33 +134,63 @@ fn show_game_over_screen(mut commands: Commands, asset_server: Res<AssetServer>)
                 .spawn((
                     Node {
                         padding: UiRect::px(30.0, 30.0, 15.0, 30.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
                         ..default()
                     },
-                    BackgroundColor(BACKGROUND_COLOR),
+                    BackgroundColor(theme.background),
                 ))
                 .with_children(|parent| {
                     parent
                         .spawn((Text::new(""), TextLayout::new_with_justify(Justify::Center)))
                         .with_children(|parent| {
                             parent.spawn((
-                                TextSpan::new("Game over!"),
+                                TextSpan::new(title),
                                 TextFont {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font: theme.font.clone(),
                                     font_size: 100.0,
                                     ..default()
                                 },
-                                TextColor(css::RED.into()),
+                                TextColor(theme.highlight),
+                            ));
+                            parent.spawn((
+                                TextSpan::new(summary),
+                                TextFont {
+                                    font: theme.font.clone(),
+                                    font_size: 30.0,
+                                    ..default()
+                                },
+                                TextColor(theme.text),
                             ));
                             parent.spawn((
-                                TextSpan::new("\nPress any key to restart"),
+                                TextSpan::new(hint),
                                 TextFont {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font: theme.font.clone(),
                                     font_size: 40.0,
                                     ..default()
                                 },
-                                TextColor(css::RED.into()),
+                                TextColor(theme.highlight),
                             ));
                         });
+                    parent.spawn((
+                        Button,
+                        Node {
+                            margin: UiRect::top(Val::Px(20.0)),
+                            padding: UiRect::axes(Val::Px(20.0), Val::Px(10.0)),
+                            ..default()
+                        },
+                        BackgroundColor(theme.text.with_alpha(0.25)),
+                        SettingsButton,
+                        children![(
+                            Text::new("Settings"),
+                            TextFont {
+                                font: theme.font.clone(),
+                                font_size: 40.0,
+                                ..default()
+                            },
+                            TextColor(theme.text),
+                        )],
+                    ));
                 });
         });
 }
@@ -67,14 +198,29 @@ fn show_game_over_screen(mut commands: Commands, asset_server: Res<AssetServer>)
 fn read_restart_input(
     time: Res<Time>,
     mut timer: ResMut<GameOverWaitTimer>,
+    actions: Res<Actions>,
     keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    settings_button: Query<&Interaction, With<SettingsButton>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
     timer.0.tick(time.delta());
     if !timer.0.is_finished() {
         return;
     }
-    if keys.get_just_pressed().next().is_some() {
+    if settings_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+        || keys.just_pressed(KeyCode::Tab)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Select))
+    {
+        next_state.set(GameState::Settings);
+    } else if actions
+        .iter()
+        .any(|action| !matches!(action, Action::Pause | Action::Quit | Action::Mute))
+    {
         next_state.set(GameState::InGame);
     }
 }
@@ -0,0 +1,65 @@
+use crate::config::Dir;
+use crate::settings::Settings;
+use bevy::input::InputSystems;
+use bevy::prelude::*;
+
+pub struct GamepadInputPlugin;
+
+impl Plugin for GamepadInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, update_sticks.after(InputSystems));
+    }
+}
+
+/// The direction the left stick of a gamepad points to, tracked to turn stick movements into
+/// presses like those of the D-pad.
+#[derive(Component, Default)]
+pub struct GamepadStick {
+    held: Option<Dir>,
+    just_pointed: Option<Dir>,
+}
+
+/// Converts a stick position into the direction it points to, or `None` inside the deadzone.
+pub fn stick_to_dir(stick: Vec2, deadzone: f32) -> Option<Dir> {
+    if stick.length() < deadzone {
+        None
+    } else if stick.x.abs() > stick.y.abs() {
+        Some(if stick.x > 0.0 { Dir::Right } else { Dir::Left })
+    } else {
+        Some(if stick.y > 0.0 { Dir::Up } else { Dir::Down })
+    }
+}
+
+/// Returns the directions pressed on the D-pad this frame, followed by the direction the left
+/// stick started pointing to this frame, if any.
+pub fn just_pressed_dirs(gamepad: &Gamepad, stick: &GamepadStick) -> Vec<Dir> {
+    let mut dirs: Vec<Dir> = [
+        (GamepadButton::DPadRight, Dir::Right),
+        (GamepadButton::DPadLeft, Dir::Left),
+        (GamepadButton::DPadUp, Dir::Up),
+        (GamepadButton::DPadDown, Dir::Down),
+    ]
+    .into_iter()
+    .filter(|(button, _)| gamepad.just_pressed(*button))
+    .map(|(_, dir)| dir)
+    .collect();
+    dirs.extend(stick.just_pointed.clone());
+    dirs
+}
+
+pub fn update_sticks(
+    settings: Res<Settings>,
+    mut gamepads: Query<(Entity, &Gamepad, Option<&mut GamepadStick>)>,
+    mut commands: Commands,
+) {
+    let deadzone = settings.stick_deadzone as f32 / 10.0;
+    for (entity, gamepad, stick) in gamepads.iter_mut() {
+        let Some(mut stick) = stick else {
+            commands.entity(entity).insert(GamepadStick::default());
+            continue;
+        };
+        let dir = stick_to_dir(gamepad.left_stick(), deadzone);
+        stick.just_pointed = if dir != stick.held { dir.clone() } else { None };
+        stick.held = dir;
+    }
+}
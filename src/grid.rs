@@ -1,4 +1,6 @@
-use crate::config::{Config, GameState};
+use crate::config::{Config, Dir, GameState, HEX_RADIUS, Position, Topology};
+use crate::settings::bounded_world;
+use crate::theme::ActiveTheme;
 use bevy::asset::RenderAssetUsages;
 use bevy::mesh::{Indices, PrimitiveTopology};
 use bevy::prelude::*;
@@ -7,64 +9,91 @@ pub struct GridPlugin;
 
 impl Plugin for GridPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::InGame), init);
+        app.add_systems(OnEnter(GameState::InGame), init.run_if(bounded_world));
     }
 }
 
-fn create_grid_mesh(config: &Config) -> Mesh {
-    let xmax = (config.grid_size_x * config.pixels_per_cell) as f32;
-    let ymax = (config.grid_size_y * config.pixels_per_cell) as f32;
-    let half_cell = config.pixels_per_cell as f32 / 2.0;
-    let line_width = 1.0; // Width of the grid lines
-    let w = line_width / 2.0;
+/// Width and height of the square chunks of cells that the grid is split into, so that only the
+/// chunks in view are drawn.
+const CHUNK_SIZE: i32 = 64;
 
-    let mut positions = Vec::new();
-    let mut indices = Vec::new();
-
-    // Add vertical lines
-    for i in 0..config.grid_size_x + 1 {
-        let x = (i * config.pixels_per_cell) as f32 - half_cell;
-        let y_start = -half_cell;
-        let y_end = ymax - half_cell;
-
-        // Create a rectangle for each line segment
-        let start_index = positions.len() as u32;
-        positions.push([x - w, y_start, 0.0]); // Bottom-left
-        positions.push([x + w, y_start, 0.0]); // Bottom-right
-        positions.push([x - w, y_end, 0.0]); // Top-left
-        positions.push([x + w, y_end, 0.0]); // Top-right
-
-        // Two triangles to form a rectangle
-        indices.push(start_index);
-        indices.push(start_index + 1);
-        indices.push(start_index + 2);
-
-        indices.push(start_index + 2);
-        indices.push(start_index + 1);
-        indices.push(start_index + 3);
+/// A mesh filling a cell, scaled by `scale`.
+pub fn cell_mesh(config: &Config, scale: f32) -> Mesh {
+    let size = config.pixels_per_cell as f32 * scale;
+    match config.topology {
+        Topology::Square => Rectangle::new(size, size).into(),
+        Topology::Hex => RegularPolygon::new(HEX_RADIUS * size, 6).into(),
     }
+}
 
-    // Add horizontal lines
-    for i in 0..config.grid_size_y + 1 {
-        let y = (i * config.pixels_per_cell) as f32 - half_cell;
-        let x_start = -half_cell;
-        let x_end = xmax - half_cell;
-
-        // Create a rectangle for each line segment
-        let start_index = positions.len() as u32;
-        positions.push([x_start, y - w, 0.0]); // Bottom-left
-        positions.push([x_start, y + w, 0.0]); // Top-left
-        positions.push([x_end, y - w, 0.0]); // Bottom-right
-        positions.push([x_end, y + w, 0.0]); // Top-right
+/// Adds a line of `width` from `from` to `to` as two triangles.
+fn push_line(
+    positions: &mut Vec<[f32; 3]>,
+    indices: &mut Vec<u32>,
+    from: Vec2,
+    to: Vec2,
+    width: f32,
+) {
+    let side = (to - from).normalize_or_zero().perp() * width / 2.0;
+    let start_index = positions.len() as u32;
+    for corner in [from - side, from + side, to - side, to + side] {
+        positions.push([corner.x, corner.y, 0.0]);
+    }
+    indices.extend([0, 1, 2, 2, 1, 3].map(|i| start_index + i));
+}
 
-        // Two triangles to form a rectangle
-        indices.push(start_index);
-        indices.push(start_index + 1);
-        indices.push(start_index + 2);
+/// Creates the lines of the grid around the cells in `cells`. On a square grid, the lines on the
+/// right and top edges are only included where `closed` is set, so that chunks next to each other
+/// don't draw the lines between them twice.
+pub fn create_grid_mesh(config: &Config, line_width: f32, cells: IRect, closed: BVec2) -> Mesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
 
-        indices.push(start_index + 2);
-        indices.push(start_index + 1);
-        indices.push(start_index + 3);
+    match config.topology {
+        Topology::Square => {
+            let ppc = config.pixels_per_cell as f32;
+            let min = cells.min.as_vec2() * ppc - ppc / 2.0;
+            let max = cells.max.as_vec2() * ppc - ppc / 2.0;
+
+            // Add vertical lines
+            for i in cells.min.x..cells.max.x + closed.x as i32 {
+                let x = i as f32 * ppc - ppc / 2.0;
+                let (from, to) = (Vec2::new(x, min.y), Vec2::new(x, max.y));
+                push_line(&mut positions, &mut indices, from, to, line_width);
+            }
+
+            // Add horizontal lines
+            for i in cells.min.y..cells.max.y + closed.y as i32 {
+                let y = i as f32 * ppc - ppc / 2.0;
+                let (from, to) = (Vec2::new(min.x, y), Vec2::new(max.x, y));
+                push_line(&mut positions, &mut indices, from, to, line_width);
+            }
+        }
+        Topology::Hex => {
+            let radius = HEX_RADIUS * config.pixels_per_cell as f32;
+            let half_edge = 30f32.to_radians();
+            for y in cells.min.y..cells.max.y {
+                for x in cells.min.x..cells.max.x {
+                    let pos = Position { x, y };
+                    let center = config.cell_center(&pos);
+                    // Each edge is drawn by the cell below or to the right of it, unless that cell
+                    // is in another chunk.
+                    for dir in Topology::Hex.dirs() {
+                        let neighbor = pos.neighbor(dir, Topology::Hex);
+                        let neighbor = IVec2::new(neighbor.x, neighbor.y);
+                        let owned = matches!(dir, Dir::Left | Dir::DownLeft | Dir::DownRight);
+                        let inside =
+                            neighbor.cmpge(cells.min).all() && neighbor.cmplt(cells.max).all();
+                        if !owned && inside {
+                            continue;
+                        }
+                        let from = center + radius * Vec2::from_angle(dir.angle() - half_edge);
+                        let to = center + radius * Vec2::from_angle(dir.angle() + half_edge);
+                        push_line(&mut positions, &mut indices, from, to, line_width);
+                    }
+                }
+            }
+        }
     }
 
     let mut mesh = Mesh::new(
@@ -81,15 +110,30 @@ fn create_grid_mesh(config: &Config) -> Mesh {
     mesh
 }
 
-fn init(
+pub fn init(
     config: Res<Config>,
+    theme: Res<ActiveTheme>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let grid_mesh = create_grid_mesh(&config);
-    let mesh_handle = meshes.add(grid_mesh);
-    let material_handle = materials.add(ColorMaterial::from(Color::WHITE));
-
-    commands.spawn((Mesh2d(mesh_handle), MeshMaterial2d(material_handle)));
+    let material_handle = materials.add(ColorMaterial::from(theme.grid));
+    for x in (0..config.grid_size_x).step_by(CHUNK_SIZE as usize) {
+        for y in (0..config.grid_size_y).step_by(CHUNK_SIZE as usize) {
+            let cells = IRect::new(
+                x,
+                y,
+                (x + CHUNK_SIZE).min(config.grid_size_x),
+                (y + CHUNK_SIZE).min(config.grid_size_y),
+            );
+            let closed = cells
+                .max
+                .cmpeq(IVec2::new(config.grid_size_x, config.grid_size_y));
+            let grid_mesh = create_grid_mesh(&config, theme.grid_line_width, cells, closed);
+            commands.spawn((
+                Mesh2d(meshes.add(grid_mesh)),
+                MeshMaterial2d(material_handle.clone()),
+            ));
+        }
+    }
 }
@@ -0,0 +1,371 @@
+use crate::board::{self, Board, Cell};
+use crate::config::{Config, Dir, GameState, Position, PreviousPosition, ZLayer, on_tick};
+use crate::player::{self, DeathCause, Player, PlayerDied, SnakeSystems};
+use crate::powerups::ActivePowerUps;
+use crate::save::Resuming;
+use crate::settings::{Settings, bounded_world, custom_board};
+use crate::theme::ActiveTheme;
+use crate::{food, grid};
+use bevy::prelude::*;
+use rand::prelude::*;
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::InGame),
+            (
+                init.after(board::init),
+                spawn_random_hazards
+                    .run_if(
+                        bounded_world
+                            .and(custom_board)
+                            .and(not(resource_exists::<Resuming>)),
+                    )
+                    .after(init)
+                    .after(player::init)
+                    .after(food::init),
+            ),
+        )
+        .add_systems(
+            Update,
+            // Hazards move before the snake, so that it runs into them where they are going to be.
+            (slide_walls, toggle_spikes, patrol_enemies)
+                .chain()
+                .in_set(MoveHazards)
+                .run_if(in_state(GameState::InGame).and(on_tick))
+                .after(SnakeSystems::Tick)
+                .before(SnakeSystems::Move),
+        );
+    }
+}
+
+/// The systems moving the hazards, once per tick before the snake moves.
+#[derive(SystemSet, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MoveHazards;
+
+/// A dynamic obstacle, as placed on a board.
+pub enum Hazard {
+    /// A wall moving back and forth along a path of neighbouring cells.
+    SlidingWall(Vec<Position>),
+    /// Spikes that come out and retract again every `period` ticks.
+    Spikes { pos: Position, period: u32 },
+    /// An enemy walking straight ahead until it is blocked, then turning clockwise.
+    Enemy { pos: Position, dir: Dir },
+}
+
+/// Marker for every hazard on the board, whichever kind it is.
+#[derive(Component)]
+pub struct Danger;
+
+#[derive(Component)]
+struct SlidingWall {
+    path: Vec<Position>,
+    index: usize,
+    forward: bool,
+}
+
+impl SlidingWall {
+    /// The index of the next cell on the path and whether that is forward.
+    fn next(&self) -> (usize, bool) {
+        let forward = if self.forward {
+            self.index + 1 < self.path.len()
+        } else {
+            self.index == 0
+        };
+        (
+            if forward {
+                self.index + 1
+            } else {
+                self.index - 1
+            },
+            forward,
+        )
+    }
+}
+
+#[derive(Component)]
+struct Spikes {
+    period: u32,
+    ticks: u32,
+    active: bool,
+}
+
+#[derive(Component)]
+struct Enemy {
+    dir: Dir,
+}
+
+#[derive(Resource)]
+pub struct HazardAssets {
+    wall_mesh: Handle<Mesh>,
+    spikes_mesh: Handle<Mesh>,
+    enemy_mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+    /// Used for retracted spikes.
+    faded_material: Handle<ColorMaterial>,
+}
+
+/// No hazards are placed this many cells or closer to the head at the start of a round.
+const START_CLEARANCE: i32 = 3;
+
+fn init(
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let cell_size = config.pixels_per_cell as f32;
+    commands.insert_resource(HazardAssets {
+        wall_mesh: meshes.add(grid::cell_mesh(&config, 1.0)),
+        spikes_mesh: meshes.add(RegularPolygon::new(cell_size * 0.4, 3)),
+        enemy_mesh: meshes.add(Circle::new(cell_size * 0.4)),
+        material: materials.add(theme.hazard),
+        faded_material: materials.add(theme.hazard.with_alpha(0.25)),
+    });
+}
+
+/// Spawns `hazard` and puts it on the board.
+pub fn spawn_hazard(
+    hazard: Hazard,
+    board: &mut Board,
+    assets: &HazardAssets,
+    commands: &mut Commands,
+) {
+    let (pos, mesh, material) = match &hazard {
+        Hazard::SlidingWall(path) => (path[0].clone(), &assets.wall_mesh, &assets.material),
+        Hazard::Spikes { pos, .. } => (pos.clone(), &assets.spikes_mesh, &assets.faded_material),
+        Hazard::Enemy { pos, .. } => (pos.clone(), &assets.enemy_mesh, &assets.material),
+    };
+    let mut entity = commands.spawn((
+        Mesh2d(mesh.clone()),
+        MeshMaterial2d(material.clone()),
+        PreviousPosition(pos.clone()),
+        pos.clone(),
+        ZLayer { z: 3 },
+        Danger,
+    ));
+    // Spikes start retracted and leave their cell free until they come out.
+    let occupies = match hazard {
+        Hazard::SlidingWall(path) => {
+            entity.insert(SlidingWall {
+                path,
+                index: 0,
+                forward: true,
+            });
+            true
+        }
+        Hazard::Spikes { period, .. } => {
+            entity.insert(Spikes {
+                period,
+                ticks: 0,
+                active: false,
+            });
+            false
+        }
+        Hazard::Enemy { dir, .. } => {
+            entity.insert(Enemy { dir });
+            true
+        }
+    };
+    if occupies {
+        board.set(&pos, Cell::Hazard(entity.id()));
+    }
+}
+
+fn spawn_random_hazards(
+    settings: Res<Settings>,
+    head: Query<&Position, With<Player>>,
+    assets: Res<HazardAssets>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    let mut rng = StdRng::seed_from_u64(getrandom::u64().unwrap_or(0));
+    let head = head.single().ok();
+    let topology = board.topology();
+    for i in 0..settings.hazards {
+        let Some(pos) = (0..100)
+            .filter_map(|_| board.random_free(IVec2::ZERO, &mut rng))
+            .find(|pos| {
+                head.is_none_or(|head| {
+                    (pos.x - head.x).abs().max((pos.y - head.y).abs()) > START_CLEARANCE
+                })
+            })
+        else {
+            return;
+        };
+        let dir = topology.dirs().choose(&mut rng).unwrap().clone();
+        let hazard = match i % 3 {
+            0 => {
+                let mut path = vec![pos];
+                while path.len() < rng.random_range(3..=6) {
+                    let next = path.last().unwrap().neighbor(&dir, topology);
+                    if board.at(&next) != Cell::Empty {
+                        break;
+                    }
+                    path.push(next);
+                }
+                if path.len() < 2 {
+                    continue;
+                }
+                Hazard::SlidingWall(path)
+            }
+            1 => Hazard::Spikes {
+                pos,
+                period: rng.random_range(4..=8),
+            },
+            _ => Hazard::Enemy { pos, dir },
+        };
+        spawn_hazard(hazard, &mut board, &assets, &mut commands);
+    }
+}
+
+/// What became of a hazard trying to move.
+#[derive(PartialEq)]
+enum Step {
+    Moved,
+    /// Something other than the snake is in the way.
+    Blocked,
+    /// The snake is in the way. The hazard stays where it is and hits the snake.
+    HitSnake,
+}
+
+/// Kills the snake a hazard ran into, unless its shield saves it.
+fn hit_snake(active: &mut ActivePowerUps, died: &mut MessageWriter<PlayerDied>) {
+    if !active.use_shield() {
+        died.write(PlayerDied {
+            cause: DeathCause::Hazard,
+        });
+    }
+}
+
+/// Moves the hazard `entity` from `pos` to `target` if the cell is free.
+fn step(
+    entity: Entity,
+    pos: &mut Position,
+    prev: &mut PreviousPosition,
+    target: Position,
+    board: &mut Board,
+    active: &mut ActivePowerUps,
+    died: &mut MessageWriter<PlayerDied>,
+) -> Step {
+    prev.0 = pos.clone();
+    match board.at(&target) {
+        Cell::Empty => {}
+        Cell::Head(_) | Cell::Tail(_) => {
+            hit_snake(active, died);
+            return Step::HitSnake;
+        }
+        _ => return Step::Blocked,
+    }
+    board.vacate(pos, entity);
+    board.set(&target, Cell::Hazard(entity));
+    *pos = target;
+    Step::Moved
+}
+
+fn slide_walls(
+    mut walls: Query<(
+        Entity,
+        &mut SlidingWall,
+        &mut Position,
+        &mut PreviousPosition,
+    )>,
+    mut board: ResMut<Board>,
+    mut active: ResMut<ActivePowerUps>,
+    mut died: MessageWriter<PlayerDied>,
+) {
+    for (entity, mut wall, mut pos, mut prev) in walls.iter_mut() {
+        let (index, forward) = wall.next();
+        let target = wall.path[index].clone();
+        // A blocked wall waits until its way is free again.
+        if step(
+            entity,
+            &mut pos,
+            &mut prev,
+            target,
+            &mut board,
+            &mut active,
+            &mut died,
+        ) == Step::Moved
+        {
+            wall.index = index;
+            wall.forward = forward;
+        }
+    }
+}
+
+fn toggle_spikes(
+    assets: Res<HazardAssets>,
+    mut spikes: Query<(
+        Entity,
+        &mut Spikes,
+        &Position,
+        &mut MeshMaterial2d<ColorMaterial>,
+    )>,
+    mut board: ResMut<Board>,
+    mut active: ResMut<ActivePowerUps>,
+    mut died: MessageWriter<PlayerDied>,
+) {
+    for (entity, mut spikes, pos, mut material) in spikes.iter_mut() {
+        spikes.ticks += 1;
+        if spikes.ticks < spikes.period {
+            continue;
+        }
+        if spikes.active {
+            board.vacate(pos, entity);
+        } else {
+            match board.at(pos) {
+                Cell::Empty => {}
+                Cell::Head(_) | Cell::Tail(_) => {
+                    hit_snake(&mut active, &mut died);
+                    continue;
+                }
+                // Wait for food and the like to leave before coming out.
+                _ => continue,
+            }
+            board.set(pos, Cell::Hazard(entity));
+        }
+        spikes.ticks = 0;
+        spikes.active = !spikes.active;
+        material.0 = if spikes.active {
+            assets.material.clone()
+        } else {
+            assets.faded_material.clone()
+        };
+    }
+}
+
+fn patrol_enemies(
+    mut enemies: Query<(Entity, &mut Enemy, &mut Position, &mut PreviousPosition)>,
+    mut board: ResMut<Board>,
+    mut active: ResMut<ActivePowerUps>,
+    mut died: MessageWriter<PlayerDied>,
+) {
+    let topology = board.topology();
+    for (entity, mut enemy, mut pos, mut prev) in enemies.iter_mut() {
+        let mut dir = enemy.dir.clone();
+        for _ in topology.dirs() {
+            let target = pos.neighbor(&dir, topology);
+            match step(
+                entity,
+                &mut pos,
+                &mut prev,
+                target,
+                &mut board,
+                &mut active,
+                &mut died,
+            ) {
+                Step::Moved => {
+                    enemy.dir = dir;
+                    break;
+                }
+                Step::HitSnake => break,
+                Step::Blocked => {}
+            }
+            dir = topology.rotated_right(&dir);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::board::{self, Board, Cell, Obstacle};
+use crate::config::{Config, GameState, Position, ZLayer};
+use crate::food::{self, FoodAssets};
+use crate::grid;
+use crate::player::{self, Player};
+use crate::score::ScoreUpdate;
+use crate::settings::{Settings, WorldMode};
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::collections::HashSet;
+
+pub struct InfiniteWorldPlugin;
+
+impl Plugin for InfiniteWorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::InGame),
+            init.after(board::init)
+                .after(player::init)
+                .after(food::init),
+        )
+        .add_systems(
+            Update,
+            (update_chunks, remember_eaten_food, score_distance)
+                .run_if(in_state(GameState::InGame).and(resource_exists::<InfiniteWorld>)),
+        );
+    }
+}
+
+/// Chunks at most this many chunks away from the head's chunk are generated.
+const LOAD_RADIUS: i32 = 1;
+
+/// Chunks more than this many chunks away from the head's chunk are removed again, unless part of
+/// the snake is still in them.
+const UNLOAD_RADIUS: i32 = 2;
+
+/// No obstacles are generated this close to where the snake starts.
+const START_CLEARANCE: i32 = 3;
+
+/// The state of an infinite world. Its chunks are generated from `seed` alone, so a chunk looks
+/// the same every time it is loaded, apart from the food that was already eaten.
+#[derive(Resource)]
+struct InfiniteWorld {
+    seed: u64,
+    start: Position,
+    /// The farthest the head has been from `start`, in cells along the axes.
+    max_distance: i32,
+    /// The chunk the head was in when chunks were last loaded and unloaded.
+    center: Option<IVec2>,
+    loaded: HashSet<IVec2>,
+    eaten: HashSet<Position>,
+    /// The grid of chunks starting on an even and on an odd row, which differ on a hex grid.
+    grid_meshes: [Handle<Mesh>; 2],
+    grid_material: Handle<ColorMaterial>,
+    wall_mesh: Handle<Mesh>,
+    wall_material: Handle<ColorMaterial>,
+}
+
+/// Marks everything that was generated for a chunk, so that it can be removed with it.
+#[derive(Component)]
+struct WorldChunk(IVec2);
+
+fn init(
+    config: Res<Config>,
+    settings: Res<Settings>,
+    theme: Res<ActiveTheme>,
+    head: Query<&Position, With<Player>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if settings.world() != WorldMode::Infinite {
+        commands.remove_resource::<InfiniteWorld>();
+        return;
+    }
+    let grid_mesh = |first_row| {
+        let chunk = IRect::new(
+            0,
+            first_row,
+            config.grid_size_x,
+            first_row + config.grid_size_y,
+        );
+        grid::create_grid_mesh(&config, theme.grid_line_width, chunk, BVec2::FALSE)
+    };
+    commands.insert_resource(InfiniteWorld {
+        seed: getrandom::u64().unwrap_or(0),
+        start: head
+            .single()
+            .map_or(Position { x: 0, y: 0 }, Position::clone),
+        max_distance: 0,
+        center: None,
+        loaded: HashSet::new(),
+        eaten: HashSet::new(),
+        grid_meshes: [meshes.add(grid_mesh(0)), meshes.add(grid_mesh(1))],
+        grid_material: materials.add(theme.grid),
+        wall_mesh: meshes.add(grid::cell_mesh(&config, 1.0)),
+        wall_material: materials.add(theme.wall),
+    });
+}
+
+/// A random number generator for `chunk` that only depends on the world's seed.
+fn chunk_rng(seed: u64, chunk: IVec2) -> StdRng {
+    StdRng::seed_from_u64(
+        seed ^ (chunk.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (chunk.y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F),
+    )
+}
+
+/// Generates the obstacles and food of `chunk` on the empty cells of the board.
+fn load_chunk(
+    chunk: IVec2,
+    world: &InfiniteWorld,
+    config: &Config,
+    board: &mut Board,
+    food_assets: &FoodAssets,
+    commands: &mut Commands,
+) {
+    let size = board.chunk_size();
+    let origin = chunk * size;
+    // Moving by an even number of rows keeps the shape of a hex grid.
+    let odd = origin.y.rem_euclid(2);
+    let offset = config.cell_center(&Position {
+        x: origin.x,
+        y: origin.y - odd,
+    });
+    commands.spawn((
+        Mesh2d(world.grid_meshes[odd as usize].clone()),
+        MeshMaterial2d(world.grid_material.clone()),
+        Transform::from_translation(offset.extend(0.0)),
+        WorldChunk(chunk),
+    ));
+
+    let mut rng = chunk_rng(world.seed, chunk);
+    let random_cell = |rng: &mut StdRng| Position {
+        x: origin.x + rng.random_range(0..size.x),
+        y: origin.y + rng.random_range(0..size.y),
+    };
+    let area = size.x * size.y;
+
+    // Short straight walls covering about one cell in forty.
+    for _ in 0..(area / 100).max(1) {
+        let start = random_cell(&mut rng);
+        let step = if rng.random_bool(0.5) {
+            IVec2::X
+        } else {
+            IVec2::Y
+        };
+        for i in 0..rng.random_range(1..=4) {
+            let pos = Position {
+                x: start.x + i * step.x,
+                y: start.y + i * step.y,
+            };
+            let near_start = (pos.x - world.start.x).abs() <= START_CLEARANCE
+                && (pos.y - world.start.y).abs() <= START_CLEARANCE;
+            if near_start || board.chunk_of(&pos) != chunk || board.at(&pos) != Cell::Empty {
+                continue;
+            }
+            commands.spawn((
+                Mesh2d(world.wall_mesh.clone()),
+                MeshMaterial2d(world.wall_material.clone()),
+                pos.clone(),
+                ZLayer { z: 1 },
+                Obstacle,
+                WorldChunk(chunk),
+            ));
+            board.set(&pos, Cell::Wall);
+        }
+    }
+
+    for _ in 0..(area / 100).max(1) {
+        let pos = random_cell(&mut rng);
+        if world.eaten.contains(&pos) || board.at(&pos) != Cell::Empty {
+            continue;
+        }
+        let food = food::spawn_at(commands, food_assets, pos.clone());
+        commands.entity(food).insert(WorldChunk(chunk));
+        board.set(&pos, Cell::Food(food));
+    }
+}
+
+/// Generates the chunks around the head and removes those far away whenever the head enters
+/// another chunk.
+fn update_chunks(
+    config: Res<Config>,
+    food_assets: Res<FoodAssets>,
+    head: Query<&Position, With<Player>>,
+    generated: Query<(Entity, &WorldChunk)>,
+    mut world: ResMut<InfiniteWorld>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    let Ok(head) = head.single() else {
+        return;
+    };
+    let center = board.chunk_of(head);
+    if world.center == Some(center) {
+        return;
+    }
+    world.center = Some(center);
+
+    let far: Vec<IVec2> = world
+        .loaded
+        .iter()
+        .filter(|chunk| {
+            (**chunk - center).abs().max_element() > UNLOAD_RADIUS && !board.has_snake(**chunk)
+        })
+        .copied()
+        .collect();
+    for chunk in &far {
+        world.loaded.remove(chunk);
+        board.unload(*chunk);
+    }
+    for (entity, WorldChunk(chunk)) in generated.iter() {
+        if far.contains(chunk) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for y in -LOAD_RADIUS..=LOAD_RADIUS {
+        for x in -LOAD_RADIUS..=LOAD_RADIUS {
+            let chunk = center + IVec2::new(x, y);
+            if world.loaded.insert(chunk) {
+                load_chunk(
+                    chunk,
+                    &world,
+                    &config,
+                    &mut board,
+                    &food_assets,
+                    &mut commands,
+                );
+            }
+        }
+    }
+}
+
+/// Keeps eaten food from coming back when its chunk is generated again.
+fn remember_eaten_food(mut updates: MessageReader<ScoreUpdate>, mut world: ResMut<InfiniteWorld>) {
+    for update in updates.read() {
+        if let ScoreUpdate::AteFood { position } = update {
+            world.eaten.insert(position.clone());
+        }
+    }
+}
+
+/// Awards a point for every cell the head gets further away from the start than before.
+fn score_distance(
+    head: Query<&Position, (With<Player>, Changed<Position>)>,
+    mut world: ResMut<InfiniteWorld>,
+    mut score: MessageWriter<ScoreUpdate>,
+) {
+    for pos in head.iter() {
+        let distance = (pos.x - world.start.x).abs() + (pos.y - world.start.y).abs();
+        while world.max_distance < distance {
+            world.max_distance += 1;
+            score.write(ScoreUpdate::Explored);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! The text format levels are written in, shared by the campaign and the custom level made in the
+//! editor.
+//!
+//! A level is a list of rows from top to bottom with one character per cell: `#` is a wall, `*`
+//! is food that is there from the start, the digits `1` to `9` are portals, linked to the other
+//! portal with the same digit, and one of `>`, `<`, `^` and `v` is where the snake starts, heading
+//! that way. Every other character, usually `.`, is an empty cell.
+
+use crate::board::{self, Board, Cell, Obstacle};
+use crate::config::{Config, Dir, GameState, Position, ZLayer};
+use crate::food::{self, FoodAssets};
+use crate::player::{self, SnakeStart};
+use crate::portal::{self, PortalAssets};
+use crate::save;
+use crate::settings::{GameMode, Settings};
+use crate::theme::ActiveTheme;
+use crate::{grid, storage};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        // A level edited by hand could be of any shape, which the board can't be built from.
+        let level = storage::load::<LevelFile>(CUSTOM_LEVEL_KEY)
+            .filter(LevelFile::is_valid)
+            .unwrap_or_default();
+        app.insert_resource(CustomLevel(level)).add_systems(
+            OnEnter(GameState::InGame),
+            (
+                build_custom_level.after(board::init).before(player::init),
+                place_custom_items
+                    .after(food::init)
+                    .after(portal::init)
+                    .before(save::RestoreRound),
+            )
+                .run_if(custom_level_mode),
+        );
+    }
+}
+
+pub const CUSTOM_LEVEL_KEY: &str = "custom_level";
+
+/// A level as it is stored in a file.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LevelFile {
+    pub name: String,
+    /// The rows of the level from top to bottom, all of the same length.
+    pub rows: Vec<String>,
+}
+
+impl Default for LevelFile {
+    /// An empty board with the snake starting at its usual place.
+    fn default() -> Self {
+        let size = IVec2::new(20, 15);
+        let start = SnakeStart::default().pos;
+        let rows = (0..size.y)
+            .map(|row| {
+                (0..size.x)
+                    .map(|x| {
+                        let y = size.y - 1 - row;
+                        if (x, y) == (start.x, start.y) {
+                            Tile::Start(Dir::Right).to_char()
+                        } else {
+                            Tile::Empty.to_char()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        LevelFile {
+            name: "Custom level".to_owned(),
+            rows,
+        }
+    }
+}
+
+impl LevelFile {
+    /// Whether the level is a rectangle of at least one cell with exactly one start.
+    fn is_valid(&self) -> bool {
+        let size = size(&self.rows);
+        let rectangular = self
+            .rows
+            .iter()
+            .all(|row| row.chars().count() == size.x as usize);
+        let starts = tiles(&self.rows)
+            .filter(|(_, tile)| matches!(tile, Tile::Start(_)))
+            .count();
+        size.x > 0 && size.y > 0 && rectangular && starts == 1
+    }
+}
+
+/// The level made in the editor and played with [`GameMode::Custom`].
+#[derive(Resource)]
+pub struct CustomLevel(pub LevelFile);
+
+/// What a cell of a level starts out with.
+#[derive(Clone, PartialEq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Food,
+    /// One end of the portal pair with this number, from 1 to 9.
+    Portal(u32),
+    /// Where the snake starts and the direction it heads in.
+    Start(Dir),
+}
+
+impl Tile {
+    pub fn from_char(c: char) -> Tile {
+        match c {
+            '#' => Tile::Wall,
+            '*' => Tile::Food,
+            '1'..='9' => Tile::Portal(c.to_digit(10).unwrap()),
+            '>' => Tile::Start(Dir::Right),
+            '<' => Tile::Start(Dir::Left),
+            '^' => Tile::Start(Dir::Up),
+            'v' => Tile::Start(Dir::Down),
+            _ => Tile::Empty,
+        }
+    }
+
+    pub fn to_char(&self) -> char {
+        match self {
+            Tile::Empty => '.',
+            Tile::Wall => '#',
+            Tile::Food => '*',
+            Tile::Portal(pair) => char::from_digit(*pair, 10).unwrap_or('.'),
+            Tile::Start(Dir::Left) => '<',
+            Tile::Start(Dir::Up) => '^',
+            Tile::Start(Dir::Down) => 'v',
+            Tile::Start(_) => '>',
+        }
+    }
+}
+
+/// Width and height of the level in cells.
+pub fn size(rows: &[impl AsRef<str>]) -> IVec2 {
+    let width = rows.first().map_or(0, |row| row.as_ref().chars().count());
+    IVec2::new(width as i32, rows.len() as i32)
+}
+
+/// Every cell of the level that is not empty, with its tile.
+pub fn tiles(rows: &[impl AsRef<str>]) -> impl Iterator<Item = (Position, Tile)> {
+    let height = rows.len() as i32;
+    rows.iter().enumerate().flat_map(move |(row, line)| {
+        line.as_ref()
+            .chars()
+            .enumerate()
+            .map(move |(x, c)| {
+                let pos = Position {
+                    x: x as i32,
+                    y: height - 1 - row as i32,
+                };
+                (pos, Tile::from_char(c))
+            })
+            .filter(|(_, tile)| *tile != Tile::Empty)
+    })
+}
+
+/// The row of `rows` that `pos` is in and its index there, or `None` if it is outside the level.
+fn locate(rows: &[impl AsRef<str>], pos: &Position) -> Option<(usize, usize)> {
+    let size = size(rows);
+    let inside = (0..size.x).contains(&pos.x) && (0..size.y).contains(&pos.y);
+    inside.then(|| ((size.y - 1 - pos.y) as usize, pos.x as usize))
+}
+
+/// The tile at `pos`, which is empty outside the level.
+pub fn tile_at(rows: &[impl AsRef<str>], pos: &Position) -> Tile {
+    locate(rows, pos)
+        .and_then(|(row, x)| rows[row].as_ref().chars().nth(x))
+        .map_or(Tile::Empty, Tile::from_char)
+}
+
+/// Replaces the tile at `pos`. Does nothing outside the level.
+pub fn set_tile(rows: &mut [String], pos: &Position, tile: &Tile) {
+    let Some((row, x)) = locate(rows, pos) else {
+        return;
+    };
+    rows[row] = rows[row]
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i == x { tile.to_char() } else { c })
+        .collect();
+}
+
+/// Puts the walls of the level on the board and sets where the snake starts. This has to happen
+/// before the snake is spawned.
+pub fn build_walls(
+    rows: &[impl AsRef<str>],
+    config: &Config,
+    theme: &ActiveTheme,
+    board: &mut Board,
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    let wall_mesh = meshes.add(grid::cell_mesh(config, 1.0));
+    let wall_material = materials.add(theme.wall);
+    for (pos, tile) in tiles(rows) {
+        match tile {
+            Tile::Wall => {
+                commands.spawn((
+                    Mesh2d(wall_mesh.clone()),
+                    MeshMaterial2d(wall_material.clone()),
+                    pos.clone(),
+                    ZLayer { z: 1 },
+                    Obstacle,
+                ));
+                board.set(&pos, Cell::Wall);
+            }
+            Tile::Start(dir) => commands.insert_resource(SnakeStart { pos, dir }),
+            _ => {}
+        }
+    }
+}
+
+/// Puts the food and portals of the level on the board. This has to happen once the assets for
+/// both exist, so after the first random food was placed, which portals push out of the way.
+pub fn place_items(
+    rows: &[impl AsRef<str>],
+    board: &mut Board,
+    food_assets: &FoodAssets,
+    portal_assets: &mut PortalAssets,
+    commands: &mut Commands,
+) {
+    let mut portals: HashMap<u32, Vec<Position>> = HashMap::new();
+    for (pos, tile) in tiles(rows) {
+        match tile {
+            Tile::Food if board.at(&pos) == Cell::Empty => {
+                let food = food::spawn_at(commands, food_assets, pos.clone());
+                board.set(&pos, Cell::Food(food));
+            }
+            Tile::Portal(pair) => portals.entry(pair).or_default().push(pos),
+            _ => {}
+        }
+    }
+    let mut pairs: Vec<_> = portals.into_iter().collect();
+    pairs.sort_by_key(|(pair, _)| *pair);
+    for (_, ends) in pairs {
+        // A portal without exactly one partner leads nowhere.
+        let [a, b] = ends.as_slice() else {
+            continue;
+        };
+        if ![a, b]
+            .iter()
+            .all(|pos| matches!(board.at(pos), Cell::Empty | Cell::Food(_)))
+        {
+            continue;
+        }
+        // Skip portals the snake could come out of into a wall or another portal, the same as
+        // random ones.
+        let next_to_each_other = a.direction_to(b, board.topology()).is_some();
+        if next_to_each_other || ![a, b].iter().all(|pos| portal::can_place(board, pos)) {
+            continue;
+        }
+        for pos in [a, b] {
+            if let Cell::Food(food) = board.at(pos) {
+                commands.entity(food).despawn();
+            }
+        }
+        portal::spawn_pair(a.clone(), b.clone(), board, portal_assets, commands);
+    }
+}
+
+/// Run condition for systems that only apply to [`GameMode::Custom`].
+pub fn custom_level_mode(settings: Res<Settings>) -> bool {
+    settings.game_mode == GameMode::Custom
+}
+
+fn build_custom_level(
+    level: Res<CustomLevel>,
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    build_walls(
+        &level.0.rows,
+        &config,
+        &theme,
+        &mut board,
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+    );
+}
+
+fn place_custom_items(
+    level: Res<CustomLevel>,
+    food_assets: Res<FoodAssets>,
+    mut portal_assets: ResMut<PortalAssets>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    place_items(
+        &level.0.rows,
+        &mut board,
+        &food_assets,
+        &mut portal_assets,
+        &mut commands,
+    );
+}
@@ -1,13 +1,34 @@
 use crate::config::*;
 use bevy::{app::MainScheduleOrder, ecs::schedule::ScheduleLabel, prelude::*};
 
+mod actions;
+mod audio;
+mod board;
 mod camera;
+mod campaign;
 mod config;
+mod daily;
+mod editor;
+mod effects;
 mod food;
 mod game_over;
+mod gamepad;
 mod grid;
+mod hazard;
+mod infinite;
+mod level;
+mod minimap;
+mod pause;
 mod player;
+mod portal;
+mod powerups;
+mod save;
 mod score;
+mod settings;
+mod storage;
+mod theme;
+mod time_attack;
+mod touch;
 
 fn despawn_all(
     all_except_necessary: Query<Entity, (Without<Camera2d>, Without<Window>)>,
@@ -20,30 +41,50 @@ fn despawn_all(
 
 fn update_transformations(
     config: Res<Config>,
-    mut query: Query<(&mut Transform, &Position, &ZLayer)>,
+    tick_timer: Option<Res<TickTimer>>,
+    mut query: Query<(
+        &mut Transform,
+        &Position,
+        &ZLayer,
+        Option<&PreviousPosition>,
+    )>,
 ) {
-    for (mut transform, pos, z_layer) in query.iter_mut() {
-        *transform = Transform::from_xyz(
-            (config.pixels_per_cell * pos.x) as f32,
-            (config.pixels_per_cell * pos.y) as f32,
-            z_layer.z as f32,
-        )
+    // How far the current tick has progressed, from 0.0 (previous cell) to 1.0 (current cell).
+    let progress = match tick_timer {
+        Some(timer) if config.smooth_movement => timer.0.fraction(),
+        _ => 1.0,
+    };
+    for (mut transform, pos, z_layer, prev) in query.iter_mut() {
+        let center = config.cell_center(pos);
+        let center = match prev {
+            Some(PreviousPosition(prev)) => config.cell_center(prev).lerp(center, progress),
+            None => center,
+        };
+        let translation = center.extend(z_layer.z as f32);
+        // Only write real changes, so that resting segments don't need their transforms propagated.
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
     }
 }
 
 #[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
 struct UpdateTransformations;
 
-pub fn close_on_esc(
+pub fn close_on_quit(
     mut commands: Commands,
     focused_windows: Query<(Entity, &Window)>,
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<actions::Actions>,
 ) {
+    if actions.just_pressed(actions::Action::Quit) {
+        close_focused_window(&mut commands, &focused_windows);
+    }
+}
+
+/// Closes the window that has the focus, which quits the game.
+pub fn close_focused_window(commands: &mut Commands, focused_windows: &Query<(Entity, &Window)>) {
     for (window, focus) in focused_windows.iter() {
-        if !focus.focused {
-            continue;
-        }
-        if input.just_pressed(KeyCode::Escape) {
+        if focus.focused {
             commands.entity(window).despawn();
         }
     }
@@ -51,33 +92,56 @@ pub fn close_on_esc(
 
 fn main() {
     let mut app = App::new();
-    app.insert_resource(ClearColor(BACKGROUND_COLOR))
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Snake".to_owned(),
-                canvas: Some("#game".to_owned()),
-                ..default()
-            }),
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            title: "Snake".to_owned(),
+            canvas: Some("#game".to_owned()),
             ..default()
-        }))
-        .init_state::<GameState>()
-        .add_plugins((
-            camera::CameraPlugin,
-            game_over::GameOverScreenPlugin,
-            grid::GridPlugin,
-            score::ScorePlugin,
-            player::PlayerPlugin,
-            food::FoodPlugin,
-        ))
-        .insert_resource(Config {
-            grid_size_x: 20,
-            grid_size_y: 20,
-            pixels_per_cell: 30,
-        })
-        .add_systems(Update, close_on_esc)
-        .add_systems(OnExit(GameState::GameOver), despawn_all)
-        .init_schedule(UpdateTransformations)
-        .add_systems(UpdateTransformations, update_transformations);
+        }),
+        ..default()
+    }))
+    .init_state::<GameState>()
+    .add_plugins((
+        camera::CameraPlugin,
+        game_over::GameOverScreenPlugin,
+        grid::GridPlugin,
+        score::ScorePlugin,
+        player::PlayerPlugin,
+        food::FoodPlugin,
+        board::BoardPlugin,
+        settings::SettingsPlugin,
+        theme::ThemePlugin,
+    ))
+    .add_plugins((
+        audio::SoundPlugin,
+        gamepad::GamepadInputPlugin,
+        touch::TouchPlugin,
+        actions::ActionsPlugin,
+        pause::PausePlugin,
+        effects::EffectsPlugin,
+        minimap::MinimapPlugin,
+        infinite::InfiniteWorldPlugin,
+        portal::PortalPlugin,
+        hazard::HazardPlugin,
+        powerups::PowerUpsPlugin,
+        campaign::CampaignPlugin,
+        time_attack::TimeAttackPlugin,
+        daily::DailyPlugin,
+    ))
+    .add_plugins((level::LevelPlugin, editor::EditorPlugin, save::SavePlugin))
+    .insert_resource(Config {
+        grid_size_x: 20,
+        grid_size_y: 20,
+        pixels_per_cell: 30,
+        smooth_movement: true,
+        topology: Topology::Square,
+    })
+    .add_systems(Update, close_on_quit.run_if(not(save::offers_save)))
+    .add_systems(OnExit(GameState::GameOver), despawn_all)
+    .add_systems(OnExit(GameState::Settings), despawn_all)
+    .add_systems(OnExit(GameState::Editor), despawn_all)
+    .init_schedule(UpdateTransformations)
+    .add_systems(UpdateTransformations, update_transformations);
 
     let mut order = app.world_mut().resource_mut::<MainScheduleOrder>();
     order.insert_after(Update, UpdateTransformations);
@@ -0,0 +1,243 @@
+use crate::board::Obstacle;
+use crate::config::{Config, GameState, Position};
+use crate::food::Food;
+use crate::hazard::Danger;
+use crate::player::{Player, TailSegment};
+use crate::portal::Portal;
+use crate::settings::{Corner, Settings, bounded_world};
+use crate::theme::ActiveTheme;
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use std::collections::HashMap;
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::InGame),
+            spawn_minimap.run_if(bounded_world),
+        )
+        .add_systems(Update, (layout_minimap, draw_minimap));
+    }
+}
+
+/// The UI node showing the minimap image, which has one pixel per cell and a one pixel wall around
+/// the board. It remembers what it drew where, so that each frame only the cells whose contents
+/// changed are drawn again.
+#[derive(Component)]
+struct Minimap {
+    image: Handle<Image>,
+    /// The cell each entity on the minimap was drawn in.
+    drawn_at: HashMap<Entity, Position>,
+    /// The entities drawn in each cell with their colors. The last one is the one visible.
+    cells: HashMap<Position, Vec<(Entity, [u8; 4])>>,
+}
+
+impl Minimap {
+    fn insert(&mut self, entity: Entity, pos: Position, color: [u8; 4]) {
+        self.drawn_at.insert(entity, pos.clone());
+        self.cells.entry(pos).or_default().push((entity, color));
+    }
+
+    /// Forgets `entity` and returns the cell it was drawn in, if it was drawn at all.
+    fn remove(&mut self, entity: Entity) -> Option<Position> {
+        let pos = self.drawn_at.remove(&entity)?;
+        if let Some(drawn) = self.cells.get_mut(&pos) {
+            drawn.retain(|&(other, _)| other != entity);
+            if drawn.is_empty() {
+                self.cells.remove(&pos);
+            }
+        }
+        Some(pos)
+    }
+}
+
+fn spawn_minimap(config: Res<Config>, mut images: ResMut<Assets<Image>>, mut commands: Commands) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: config.grid_size_x as u32 + 2,
+            height: config.grid_size_y as u32 + 2,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    let handle = images.add(image);
+    commands.spawn((
+        ImageNode::new(handle.clone()),
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        Minimap {
+            image: handle,
+            drawn_at: HashMap::new(),
+            cells: HashMap::new(),
+        },
+    ));
+}
+
+/// Sizes the minimap to the configured share of the shorter window side and moves it to the
+/// configured corner.
+fn layout_minimap(
+    config: Res<Config>,
+    settings: Res<Settings>,
+    mut minimap: Query<(&mut Node, &mut Visibility), With<Minimap>>,
+    added: Query<(), Added<Minimap>>,
+) {
+    if !settings.is_changed() && added.is_empty() {
+        return;
+    }
+    let width = config.grid_size_x as f32 + 2.0;
+    let height = config.grid_size_y as f32 + 2.0;
+    let size = settings.minimap_size as f32 / width.max(height);
+    for (mut node, mut visibility) in minimap.iter_mut() {
+        *visibility = if settings.minimap_corner == Corner::Off {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        node.width = Val::VMin(width * size);
+        node.height = Val::VMin(height * size);
+        let margin = Val::Px(10.0);
+        let (top, bottom) = match settings.minimap_corner {
+            Corner::BottomLeft | Corner::BottomRight => (Val::Auto, margin),
+            _ => (margin, Val::Auto),
+        };
+        let (left, right) = match settings.minimap_corner {
+            Corner::TopLeft | Corner::BottomLeft => (margin, Val::Auto),
+            _ => (Val::Auto, margin),
+        };
+        node.top = top;
+        node.bottom = bottom;
+        node.left = left;
+        node.right = right;
+    }
+}
+
+/// Everything drawn on the minimap: heads, tail segments, food, portals, walls and hazards.
+type BoardQuery<'w, 's, F = ()> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Position,
+        AnyOf<(
+            &'static Player,
+            &'static TailSegment,
+            &'static Food,
+            &'static Portal,
+            &'static Obstacle,
+            &'static Danger,
+        )>,
+    ),
+    F,
+>;
+
+/// Which of the [`BoardQuery`] components something drawn on the minimap has.
+type Kind<'a> = (
+    Option<&'a Player>,
+    Option<&'a TailSegment>,
+    Option<&'a Food>,
+    Option<&'a Portal>,
+    Option<&'a Obstacle>,
+    Option<&'a Danger>,
+);
+
+/// The color of something drawn on the minimap.
+fn color(theme: &ActiveTheme, (head, segment, _, portal, obstacle, danger): Kind) -> [u8; 4] {
+    let color = if head.is_some() {
+        theme.head_fill
+    } else if segment.is_some() {
+        theme.tail
+    } else if let Some(portal) = portal {
+        theme.portal_color(portal.pair)
+    } else if obstacle.is_some() {
+        theme.wall
+    } else if danger.is_some() {
+        theme.hazard
+    } else {
+        theme.food
+    };
+    color.to_srgba().to_u8_array()
+}
+
+/// Draws the cells whose contents moved, appeared or disappeared since the last frame, or the
+/// whole minimap when it is new or the theme changed.
+fn draw_minimap(
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut minimap: Query<&mut Minimap>,
+    changed: BoardQuery<Changed<Position>>,
+    mut removed: RemovedComponents<Position>,
+    board: BoardQuery,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok(mut minimap) = minimap.single_mut() else {
+        removed.clear();
+        return;
+    };
+    let full = minimap.is_added() || theme.is_changed();
+    let mut dirty = Vec::new();
+    if full {
+        removed.clear();
+        minimap.drawn_at.clear();
+        minimap.cells.clear();
+        for (entity, pos, kind) in board.iter() {
+            minimap.insert(entity, pos.clone(), color(&theme, kind));
+            dirty.push(pos.clone());
+        }
+    } else {
+        for entity in removed.read() {
+            dirty.extend(minimap.remove(entity));
+        }
+        for (entity, pos, kind) in changed.iter() {
+            dirty.extend(minimap.remove(entity));
+            minimap.insert(entity, pos.clone(), color(&theme, kind));
+            dirty.push(pos.clone());
+        }
+        if dirty.is_empty() {
+            return;
+        }
+    }
+    let Some(data) = images
+        .get_mut(&minimap.image)
+        .and_then(|image| image.data.as_mut())
+    else {
+        return;
+    };
+    let width = config.grid_size_x as usize + 2;
+    let height = config.grid_size_y as usize + 2;
+    let background = theme.background.to_srgba().to_u8_array();
+    if full {
+        let wall = theme.grid.to_srgba().to_u8_array();
+        for row in 0..height {
+            for col in 0..width {
+                let is_wall = row == 0 || col == 0 || row == height - 1 || col == width - 1;
+                let i = (row * width + col) * 4;
+                data[i..i + 4].copy_from_slice(if is_wall { &wall } else { &background });
+            }
+        }
+    }
+    for pos in dirty {
+        if pos.x < 0 || pos.y < 0 || pos.x >= config.grid_size_x || pos.y >= config.grid_size_y {
+            continue;
+        }
+        let color = minimap
+            .cells
+            .get(&pos)
+            .and_then(|drawn| drawn.last())
+            .map_or(background, |&(_, color)| color);
+        // Image rows go down while board rows go up.
+        let row = height - 2 - pos.y as usize;
+        let col = pos.x as usize + 1;
+        let i = (row * width + col) * 4;
+        data[i..i + 4].copy_from_slice(&color);
+    }
+}
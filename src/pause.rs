@@ -0,0 +1,73 @@
+use crate::actions::{Action, Actions};
+use crate::config::GameState;
+use crate::save::QuitPrompt;
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnExit(GameState::InGame), unpause)
+            .add_systems(
+                Update,
+                toggle_pause
+                    .run_if(in_state(GameState::InGame).and(not(resource_exists::<QuitPrompt>))),
+            );
+    }
+}
+
+/// Marker for the text shown while the game is paused.
+#[derive(Component)]
+struct PauseText;
+
+fn toggle_pause(
+    actions: Res<Actions>,
+    theme: Res<ActiveTheme>,
+    mut time: ResMut<Time<Virtual>>,
+    pause_text: Query<Entity, With<PauseText>>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::Pause) {
+        return;
+    }
+    if time.is_paused() {
+        time.unpause();
+        for entity in pause_text.iter() {
+            commands.entity(entity).despawn();
+        }
+    } else {
+        time.pause();
+        commands.spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            PauseText,
+            children![(
+                Text::new("Paused"),
+                TextFont {
+                    font: theme.font.clone(),
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(theme.highlight),
+            )],
+        ));
+    }
+}
+
+fn unpause(
+    mut time: ResMut<Time<Virtual>>,
+    pause_text: Query<Entity, With<PauseText>>,
+    mut commands: Commands,
+) {
+    time.unpause();
+    for entity in pause_text.iter() {
+        commands.entity(entity).despawn();
+    }
+}
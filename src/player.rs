@@ -1,157 +1,364 @@
+use crate::actions::{Action, Actions};
+use crate::board::{Board, Cell};
 use crate::config::*;
-use bevy::asset::RenderAssetUsages;
-use bevy::color::palettes::css;
-use bevy::mesh::{Indices, PrimitiveTopology};
+use crate::settings::{ControlScheme, InputBuffering, Settings};
+use crate::theme::ActiveTheme;
+use bevy::image::{ImageLoaderSettings, ImageSampler};
 use bevy::prelude::*;
 use std::collections::VecDeque;
+use std::time::Duration;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::InGame), init)
+        app.add_message::<PlayerDied>()
+            .add_message::<PlayerTurned>()
+            .add_message::<PlayerGrew>()
+            .add_systems(OnEnter(GameState::InGame), init.after(crate::board::init))
             .add_systems(
                 Update,
                 (
+                    assign_gamepads.before(read_player_input),
                     read_player_input.before(apply_player_input),
-                    apply_player_input.before(move_player),
+                    apply_player_input
+                        .in_set(SnakeSystems::Tick)
+                        .before(move_player),
                     move_player
-                        .before(check_player_collision)
-                        .before(check_player_food_collision),
-                    check_player_food_collision,
-                    check_player_collision,
+                        .in_set(SnakeSystems::Move)
+                        .run_if(on_tick)
+                        .before(SnakeSystems::Collide),
+                    check_player_food_collision
+                        .in_set(SnakeSystems::Collide)
+                        .run_if(on_tick),
+                    check_player_collision
+                        .in_set(SnakeSystems::Collide)
+                        .run_if(on_tick),
+                    occupy_head_cell
+                        .run_if(on_tick)
+                        .after(SnakeSystems::Collide),
+                    handle_death.after(SnakeSystems::Collide),
+                    update_round_time,
+                    rotate_head.after(apply_player_input),
+                    update_tail_sprites
+                        .run_if(on_tick)
+                        .after(SnakeSystems::Collide),
                 )
                     .run_if(in_state(GameState::InGame)),
             );
     }
 }
 
-fn create_head_mesh(size: f32) -> Mesh {
-    // Two rectangles on top of each other to create a rectangle with an outline.
-    let s = size / 2.0 + 2.5; // outer size
-    let t = size / 2.0 - 2.5; // inner size
-    let o = LinearRgba::from(css::WHITE).to_f32_array(); // outer color
-    let i = LinearRgba::from(css::GREEN).to_f32_array(); // inner color
-    Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::default(),
-    )
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        vec![
-            [-s, -s, 0.0],
-            [s, -s, 0.0],
-            [s, s, 0.0],
-            [-s, s, 0.0],
-            [-t, -t, 1.0],
-            [t, -t, 1.0],
-            [t, t, 1.0],
-            [-t, t, 1.0],
-        ],
-    )
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_COLOR,
-        vec![
-            o, o, o, o, //
-            i, i, i, i, //
-        ],
-    )
-    .with_inserted_indices(Indices::U32(vec![
-        0, 1, 3, //
-        1, 2, 3, //
-        4, 5, 7, //
-        5, 6, 7, //
-    ]))
-}
-
-fn init(
+pub fn init(
     config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    asset_server: Res<AssetServer>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut board: ResMut<Board>,
+    start: Option<Res<SnakeStart>>,
 ) {
     commands.insert_resource(InputQueue::default());
     commands.insert_resource(TickTimer(Timer::from_seconds(0.2, TimerMode::Repeating)));
     commands.insert_resource(Tail::default());
+    commands.insert_resource(RoundStats::default());
 
     let assets = PlayerAssets {
-        head_mesh: meshes.add(create_head_mesh(config.pixels_per_cell as f32 - 3.0)),
-        head_material: materials.add(ColorMaterial::default()),
-        tail_mesh: meshes.add(Rectangle::new(
-            config.pixels_per_cell as f32 - 3.0,
-            config.pixels_per_cell as f32 - 3.0,
+        atlas: asset_server
+            .load_with_settings("sprites/snake.png", |settings: &mut ImageLoaderSettings| {
+                settings.sampler = ImageSampler::nearest()
+            }),
+        atlas_layout: layouts.add(TextureAtlasLayout::from_grid(
+            UVec2::splat(SPRITE_SIZE),
+            4,
+            1,
+            None,
+            None,
         )),
-        tail_material: materials.add(Color::from(css::LIMEGREEN)),
+        cell_size: config.pixels_per_cell as f32,
+        head_color: theme.head_fill,
+        tail_color: theme.tail,
     };
     commands.insert_resource(assets.clone());
-    commands.spawn(PlayerBundle::new(&assets));
+    let start = start.map_or_else(SnakeStart::default, |start| start.clone());
+    // The next round starts at the default place unless told otherwise again.
+    commands.remove_resource::<SnakeStart>();
+    let bundle = PlayerBundle::new(&assets, start);
+    let head_pos = bundle.pos.clone();
+    let head = commands
+        .spawn(bundle)
+        .with_children(|parent| {
+            // A slightly larger square behind the head to give it an outline.
+            parent.spawn((
+                Sprite {
+                    color: theme.head_outline,
+                    custom_size: Some(Vec2::splat(assets.cell_size + 2.0)),
+                    ..default()
+                },
+                Transform::from_xyz(0.0, 0.0, -1.0),
+            ));
+        })
+        .id();
+    board.set(&head_pos, Cell::Head(head));
+}
+
+/// Size of one sprite in the snake atlas in pixels.
+const SPRITE_SIZE: u32 = 32;
+
+/// The pieces in the snake atlas. All pieces are drawn facing right: the head looks to the right,
+/// the straight piece and the tail tip connect to the right and the corner connects left and down.
+#[derive(Clone, Copy)]
+enum SnakePiece {
+    Head = 0,
+    Straight = 1,
+    Corner = 2,
+    TailTip = 3,
+}
+
+/// Steps of a game tick, for systems that have to run between them.
+#[derive(SystemSet, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SnakeSystems {
+    /// Advances the [`TickTimer`] and applies the next turn.
+    Tick,
+    /// Moves the snake by one cell.
+    Move,
+    /// Looks at what the head moved into, before the head claims its cell on the [`Board`].
+    Collide,
+}
+
+/// Where the snake starts and the direction it heads in, used up by the next round that starts.
+/// The default start is used when this resource doesn't exist.
+#[derive(Resource, Clone)]
+pub struct SnakeStart {
+    pub pos: Position,
+    pub dir: Dir,
+}
+
+impl Default for SnakeStart {
+    fn default() -> Self {
+        SnakeStart {
+            pos: Position { x: 5, y: 5 },
+            dir: Dir::Right,
+        }
+    }
 }
 
+/// Lets the snake move through its own tail.
+#[derive(Component)]
+pub struct PassesThroughTail;
+
+/// Keeps the snake where it is for the next tick, after which it moves on as usual.
+#[derive(Component)]
+pub struct HoldsStill;
+
 /// Marker to identify the player entity, the head of the snake.
 #[derive(Component)]
-struct Player;
+pub struct Player;
 
 /// Marker to identify the segments of the snake other than its head.
 #[derive(Component)]
-struct TailSegment;
+pub struct TailSegment;
+
+/// The gamepad steering this snake, if any. Each gamepad steers at most one snake.
+#[derive(Component, Default)]
+struct PlayerGamepad(Option<Entity>);
 
 /// If non-zero, grow the snake by this many segments.
 #[derive(Component)]
-struct SegmentsToGrow(u32);
+pub struct SegmentsToGrow(pub u32);
 
 /// A list of all snake segments excluding its head, oldest segment first.
 #[derive(Default, Resource)]
-struct Tail {
+pub struct Tail {
     segments: VecDeque<Entity>,
 }
 
+impl Tail {
+    /// The oldest segment, at the very end of the snake.
+    pub fn end(&self) -> Option<Entity> {
+        self.segments.front().copied()
+    }
+
+    /// The newest segment, right behind the head.
+    pub fn newest(&self) -> Option<Entity> {
+        self.segments.back().copied()
+    }
+
+    /// All segments, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.segments.iter().copied()
+    }
+
+    /// Reorders the segments after the head swapped places with the end of the tail. The end, now
+    /// where the head was, stays the oldest segment and the others turn around behind the new head.
+    pub fn turn_around(&mut self) {
+        let segments = self.segments.make_contiguous();
+        segments.reverse();
+        segments.rotate_right(1);
+    }
+}
+
+impl FromIterator<Entity> for Tail {
+    /// Collects segments given oldest first.
+    fn from_iter<I: IntoIterator<Item = Entity>>(segments: I) -> Self {
+        Tail {
+            segments: segments.into_iter().collect(),
+        }
+    }
+}
+
+/// Statistics about the current round, collected while the snake is alive.
+#[derive(Default, Resource)]
+pub struct RoundStats {
+    pub time_survived: Duration,
+    pub food_eaten: u32,
+}
+
+/// The side of the board the snake ran into.
+#[derive(Clone, Copy, Debug)]
+pub enum Wall {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// What killed the snake.
+#[derive(Clone, Debug)]
+pub enum DeathCause {
+    Wall(Wall),
+    /// The snake ran into an obstacle inside the board.
+    Obstacle,
+    /// The snake ran into a hazard or a hazard ran into the snake.
+    Hazard,
+    /// The time limit of the level ran out.
+    OutOfTime,
+    /// The snake bit itself. Segments are counted from the head, starting at 1.
+    Tail {
+        segment: usize,
+    },
+}
+
+/// Written when the snake dies. Any system can write this message to end the round.
+#[derive(Message, Clone)]
+pub struct PlayerDied {
+    pub cause: DeathCause,
+}
+
+/// Written when the snake changes direction.
+#[derive(Message)]
+pub struct PlayerTurned;
+
+/// Written when the snake grows, with the tail segment that was added.
+#[derive(Message)]
+pub struct PlayerGrew {
+    pub segment: Entity,
+}
+
+/// Summary of the round that just ended, inserted when the snake dies.
 #[derive(Resource)]
-struct TickTimer(Timer);
+pub struct DeathReport {
+    pub cause: DeathCause,
+    pub length: usize,
+    pub time_survived: Duration,
+    pub food_eaten: u32,
+}
 
 #[derive(Resource, Clone)]
-struct PlayerAssets {
-    head_mesh: Handle<Mesh>,
-    head_material: Handle<ColorMaterial>,
-    tail_mesh: Handle<Mesh>,
-    tail_material: Handle<ColorMaterial>,
+pub struct PlayerAssets {
+    atlas: Handle<Image>,
+    atlas_layout: Handle<TextureAtlasLayout>,
+    cell_size: f32,
+    head_color: Color,
+    tail_color: Color,
+}
+
+impl PlayerAssets {
+    fn sprite(&self, piece: SnakePiece, color: Color, size: f32) -> Sprite {
+        Sprite {
+            color,
+            custom_size: Some(Vec2::splat(size)),
+            ..Sprite::from_atlas_image(
+                self.atlas.clone(),
+                TextureAtlas {
+                    layout: self.atlas_layout.clone(),
+                    index: piece as usize,
+                },
+            )
+        }
+    }
 }
 
 #[derive(Bundle)]
 struct PlayerBundle {
     player: Player,
     pos: Position,
+    prev_pos: PreviousPosition,
     z_layer: ZLayer,
     velocity: Velocity,
     segments_to_grow: SegmentsToGrow,
-    mesh: Mesh2d,
-    material: MeshMaterial2d<ColorMaterial>,
+    gamepad: PlayerGamepad,
+    sprite: Sprite,
 }
 
 impl PlayerBundle {
-    fn new(assets: &PlayerAssets) -> PlayerBundle {
+    fn new(assets: &PlayerAssets, start: SnakeStart) -> PlayerBundle {
         PlayerBundle {
             player: Player,
-            pos: Position { x: 5, y: 5 },
+            pos: start.pos.clone(),
+            prev_pos: PreviousPosition(start.pos),
             z_layer: ZLayer { z: 10 },
-            velocity: Velocity { dir: Dir::Right },
+            velocity: Velocity { dir: start.dir },
             segments_to_grow: SegmentsToGrow(3),
-            mesh: Mesh2d(assets.head_mesh.clone()),
-            material: MeshMaterial2d(assets.head_material.clone()),
+            gamepad: PlayerGamepad::default(),
+            sprite: assets.sprite(SnakePiece::Head, assets.head_color, assets.cell_size - 2.0),
         }
     }
 }
 
-fn spawn_segment(pos: Position, tail: &mut Tail, commands: &mut Commands, assets: &PlayerAssets) {
+fn spawn_segment(
+    pos: Position,
+    tail: &mut Tail,
+    commands: &mut Commands,
+    assets: &PlayerAssets,
+) -> Entity {
     let segment_id = commands
         .spawn((
-            Mesh2d(assets.tail_mesh.clone()),
-            MeshMaterial2d(assets.tail_material.clone()),
+            assets.sprite(SnakePiece::Straight, assets.tail_color, assets.cell_size),
             TailSegment,
             pos,
             ZLayer { z: 8 },
         ))
         .id();
     tail.segments.push_back(segment_id);
+    segment_id
+}
+
+/// Gives the snake a tail at `positions`, oldest segment first, behind the head at `head_pos`. Used
+/// to resume a saved round, when the snake doesn't grow one segment at a time.
+pub fn restore_tail(
+    positions: &[Position],
+    head_pos: &Position,
+    tail: &mut Tail,
+    board: &mut Board,
+    commands: &mut Commands,
+    assets: &PlayerAssets,
+) {
+    for (i, pos) in positions.iter().enumerate() {
+        let segment = spawn_segment(pos.clone(), tail, commands, assets);
+        board.set(pos, Cell::Tail(segment));
+        let older = i.checked_sub(1).map(|i| &positions[i]);
+        let newer = positions.get(i + 1).unwrap_or(head_pos);
+        let (piece, angle) = tail_piece(
+            board.topology(),
+            older.and_then(|older| board.direction(pos, older)),
+            board.direction(pos, newer),
+        );
+        commands.entity(segment).insert((
+            assets.sprite(piece, assets.tail_color, assets.cell_size),
+            Transform::from_rotation(Quat::from_rotation_z(angle)),
+        ));
+    }
 }
 
 #[derive(Default, Resource)]
@@ -163,23 +370,72 @@ impl InputQueue {
     fn pop_last_input(&mut self) -> Option<Dir> {
         self.queue.pop_front()
     }
-    fn insert_input(&mut self, dir: Dir) {
+    fn insert_input(&mut self, dir: Dir, settings: &Settings) {
+        if settings.reject_queued_reversals
+            && self.queue.back().is_some_and(|last| dir == last.opposite())
+        {
+            return;
+        }
+        match settings.input_buffering {
+            InputBuffering::LatestWins => self.queue.clear(),
+            InputBuffering::Queue if self.queue.len() >= settings.input_buffer_size as usize => {
+                return;
+            }
+            InputBuffering::Queue => {}
+        }
         self.queue.push_back(dir);
     }
 }
 
-fn read_player_input(keys: Res<ButtonInput<KeyCode>>, mut input_queue: ResMut<InputQueue>) {
-    if keys.just_pressed(KeyCode::ArrowRight) {
-        input_queue.insert_input(Dir::Right);
+/// Gives every snake without a connected gamepad the first gamepad not used by another snake.
+fn assign_gamepads(gamepads: Query<Entity, With<Gamepad>>, mut players: Query<&mut PlayerGamepad>) {
+    for mut assigned in players.iter_mut() {
+        if assigned.0.is_some_and(|pad| !gamepads.contains(pad)) {
+            assigned.0 = None;
+        }
     }
-    if keys.just_pressed(KeyCode::ArrowLeft) {
-        input_queue.insert_input(Dir::Left);
+    for pad in gamepads.iter() {
+        if players.iter().any(|assigned| assigned.0 == Some(pad)) {
+            continue;
+        }
+        if let Some(mut free) = players.iter_mut().find(|assigned| assigned.0.is_none()) {
+            free.0 = Some(pad);
+        }
     }
-    if keys.just_pressed(KeyCode::ArrowUp) {
-        input_queue.insert_input(Dir::Up);
+}
+
+fn read_player_input(
+    actions: Res<Actions>,
+    config: Res<Config>,
+    settings: Res<Settings>,
+    time: Res<Time<Virtual>>,
+    player: Query<(&PlayerGamepad, &Velocity), With<Player>>,
+    mut input_queue: ResMut<InputQueue>,
+) {
+    if time.is_paused() {
+        return;
     }
-    if keys.just_pressed(KeyCode::ArrowDown) {
-        input_queue.insert_input(Dir::Down);
+    let (pad, velocity) = player.single().unwrap();
+    for action in actions.for_player(pad.0) {
+        // Relative turns start from where the snake will be heading once the queued turns are done.
+        // A turn that replaces the queued one starts from the current direction instead.
+        let heading = match settings.input_buffering {
+            InputBuffering::Queue => input_queue.queue.back().unwrap_or(&velocity.dir),
+            InputBuffering::LatestWins => &velocity.dir,
+        };
+        let topology = config.topology;
+        let dir = match (settings.control_scheme, action) {
+            (ControlScheme::Relative, Action::TurnLeft) => topology.rotated_left(heading),
+            (ControlScheme::Relative, Action::TurnRight) => topology.rotated_right(heading),
+            (ControlScheme::Relative, _) => continue,
+            (ControlScheme::Absolute, _) => {
+                match action.dir().and_then(|dir| topology.steer(&dir, heading)) {
+                    Some(dir) => dir,
+                    None => continue,
+                }
+            }
+        };
+        input_queue.insert_input(dir, &settings);
     }
 }
 
@@ -188,6 +444,7 @@ fn apply_player_input(
     mut input_queue: ResMut<InputQueue>,
     mut timer: ResMut<TickTimer>,
     mut query: Query<&mut Velocity, With<Player>>,
+    mut turned: MessageWriter<PlayerTurned>,
 ) {
     if !timer.0.tick(time.delta()).just_finished() {
         return;
@@ -200,64 +457,240 @@ fn apply_player_input(
         };
         if input != velocity.dir && input != velocity.dir.opposite() {
             velocity.dir = input;
+            turned.write(PlayerTurned);
             return;
         }
     }
 }
 
+type MovingHeadQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Position,
+        &'static mut PreviousPosition,
+        &'static Velocity,
+        &'static mut SegmentsToGrow,
+        Has<HoldsStill>,
+    ),
+    With<Player>,
+>;
+
 fn move_player(
-    timer: ResMut<TickTimer>,
-    mut query: Query<(&mut Position, &Velocity, &mut SegmentsToGrow), With<Player>>,
+    mut query: MovingHeadQuery,
+    segment_query: Query<&Position, (With<TailSegment>, Without<Player>)>,
     mut tail: ResMut<Tail>,
+    mut board: ResMut<Board>,
     mut commands: Commands,
     assets: Res<PlayerAssets>,
+    mut grew: MessageWriter<PlayerGrew>,
 ) {
-    if !timer.0.just_finished() {
+    let (head, mut pos, mut prev_pos, velocity, mut to_grow, holds_still) =
+        query.single_mut().unwrap();
+    if holds_still {
+        commands.entity(head).remove::<HoldsStill>();
+        prev_pos.0 = pos.clone();
+        if let Some(end) = tail.end() {
+            commands.entity(end).remove::<PreviousPosition>();
+        }
         return;
     }
-    let (mut pos, velocity, mut to_grow) = query.single_mut().unwrap();
     if velocity.dir != Dir::None {
-        spawn_segment(pos.clone(), &mut tail, &mut commands, &assets);
+        let segment = spawn_segment(pos.clone(), &mut tail, &mut commands, &assets);
+        board.set(&pos, Cell::Tail(segment));
         if to_grow.0 == 0 {
-            commands
-                .entity(tail.segments.pop_front().unwrap())
-                .despawn();
+            let removed = tail.segments.pop_front().unwrap();
+            if let Ok(removed_pos) = segment_query.get(removed) {
+                board.vacate(removed_pos, removed);
+                // Let the new end of the tail slide out of the cell that was just vacated, unless
+                // they are on either side of a portal.
+                if let Some(&end) = tail.segments.front() {
+                    let end_pos = if end == segment {
+                        Some(pos.as_ref())
+                    } else {
+                        segment_query.get(end).ok()
+                    };
+                    if end_pos.is_some_and(|end_pos| {
+                        removed_pos
+                            .direction_to(end_pos, board.topology())
+                            .is_some()
+                    }) {
+                        commands
+                            .entity(end)
+                            .insert(PreviousPosition(removed_pos.clone()));
+                    }
+                }
+            }
+            commands.entity(removed).despawn();
         } else {
             to_grow.0 -= 1;
+            grew.write(PlayerGrew { segment });
+            if let Some(&end) = tail.segments.front() {
+                commands.entity(end).remove::<PreviousPosition>();
+            }
         }
     }
-    pos.apply_offset(&velocity.dir);
+    *pos = board.neighbor(&pos, &velocity.dir);
+    // Coming out of a portal, slide out of its partner rather than across the board.
+    prev_pos.0 = pos.neighbor(&velocity.dir.opposite(), board.topology());
+}
+
+/// Claims the cell the head moved into, once the collision checks have seen what was there.
+fn occupy_head_cell(player: Query<(Entity, &Position), With<Player>>, mut board: ResMut<Board>) {
+    let (head, pos) = player.single().unwrap();
+    board.set(pos, Cell::Head(head));
+}
+
+/// Returns the atlas piece for a tail segment and its rotation, given the directions from the
+/// segment to its older neighbour (`None` for the tail tip) and to its newer neighbour.
+fn tail_piece(topology: Topology, older: Option<Dir>, newer: Option<Dir>) -> (SnakePiece, f32) {
+    match (older, newer) {
+        (None, Some(newer)) => (SnakePiece::TailTip, newer.angle()),
+        (Some(older), Some(newer)) if older == newer.opposite() => {
+            (SnakePiece::Straight, newer.angle())
+        }
+        (Some(older), Some(newer)) if topology == Topology::Hex => {
+            // There is no piece for the bends of a hex grid, so lay a straight piece across them.
+            let through = Vec2::from_angle(newer.angle()) - Vec2::from_angle(older.angle());
+            (SnakePiece::Straight, through.to_angle())
+        }
+        (Some(older), Some(newer)) => {
+            // The corner piece connects left and down, i.e. its second arm is a quarter turn
+            // counterclockwise from its first arm. Rotate the first arm from left onto `first`.
+            let counterclockwise =
+                Vec2::from_angle(older.angle()).perp_dot(Vec2::from_angle(newer.angle())) > 0.0;
+            let first = if counterclockwise { older } else { newer };
+            (SnakePiece::Corner, first.angle() - Dir::Left.angle())
+        }
+        // Not connected to anything in a straight line, e.g. a snake of length one.
+        _ => (SnakePiece::Straight, 0.0),
+    }
+}
+
+fn rotate_head(mut player: Query<(&Velocity, &mut Transform), With<Player>>) {
+    let (velocity, mut transform) = player.single_mut().unwrap();
+    transform.rotation = Quat::from_rotation_z(velocity.dir.angle());
+}
+
+/// Updates the sprites at both ends of the tail, the only ones whose neighbours change when the
+/// snake moves.
+fn update_tail_sprites(
+    tail: Res<Tail>,
+    board: Res<Board>,
+    player: Query<&Position, With<Player>>,
+    mut segment_query: Query<(&Position, &mut Sprite, &mut Transform), With<TailSegment>>,
+) {
+    let head_pos = player.single().unwrap();
+    let position = |i: usize| -> Option<Position> {
+        match tail.segments.get(i) {
+            Some(&segment) => segment_query
+                .get(segment)
+                .ok()
+                .map(|(pos, _, _)| pos.clone()),
+            None => (i == tail.segments.len()).then(|| head_pos.clone()),
+        }
+    };
+    let last = tail.segments.len().saturating_sub(1);
+    let ends: Vec<_> = [0, last]
+        .into_iter()
+        .filter_map(|i| {
+            let &segment = tail.segments.get(i)?;
+            let pos = position(i)?;
+            let older = i.checked_sub(1).and_then(position);
+            let newer = position(i + 1);
+            let piece = tail_piece(
+                board.topology(),
+                older.and_then(|p| board.direction(&pos, &p)),
+                newer.and_then(|p| board.direction(&pos, &p)),
+            );
+            Some((segment, piece))
+        })
+        .collect();
+    for (segment, (piece, angle)) in ends {
+        let Ok((_, mut sprite, mut transform)) = segment_query.get_mut(segment) else {
+            continue;
+        };
+        if let Some(atlas) = sprite.texture_atlas.as_mut() {
+            atlas.index = piece as usize;
+        }
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
+
+fn update_round_time(time: Res<Time>, mut stats: ResMut<RoundStats>) {
+    stats.time_survived += time.delta();
 }
 
 fn check_player_food_collision(
     mut player: Query<(&Position, &mut SegmentsToGrow), With<Player>>,
-    food_query: Query<(Entity, &Position), With<crate::food::Food>>,
+    board: Res<Board>,
     mut commands: Commands,
+    mut stats: ResMut<RoundStats>,
     mut score: MessageWriter<crate::score::ScoreUpdate>,
 ) {
     let (player_pos, mut to_grow) = player.single_mut().unwrap();
-    for (food, food_pos) in food_query.iter() {
-        if player_pos == food_pos {
-            to_grow.0 += 2;
-            commands.entity(food).despawn();
-            score.write(crate::score::ScoreUpdate::AteFood);
-        }
+    if let Cell::Food(food) = board.at(player_pos) {
+        to_grow.0 += 2;
+        stats.food_eaten += 1;
+        commands.entity(food).despawn();
+        score.write(crate::score::ScoreUpdate::AteFood {
+            position: player_pos.clone(),
+        });
     }
 }
 
 fn check_player_collision(
-    mut next_state: ResMut<NextState<GameState>>,
     config: Res<Config>,
-    player: Query<&Position, With<Player>>,
-    segment_query: Query<&Position, With<TailSegment>>,
+    tail: Res<Tail>,
+    board: Res<Board>,
+    player: Query<(&Position, Has<PassesThroughTail>), With<Player>>,
+    mut died: MessageWriter<PlayerDied>,
 ) {
-    let pos = player.single().unwrap();
-    if pos.x < 0
-        || pos.x >= config.grid_size_x
-        || pos.y < 0
-        || pos.y >= config.grid_size_y
-        || segment_query.iter().any(|p| p == pos)
-    {
-        next_state.set(GameState::GameOver);
-    }
+    let (pos, passes_through_tail) = player.single().unwrap();
+    let cause = match board.at(pos) {
+        Cell::Wall if !board.contains(pos) => DeathCause::Wall(if pos.x < 0 {
+            Wall::Left
+        } else if pos.x >= config.grid_size_x {
+            Wall::Right
+        } else if pos.y < 0 {
+            Wall::Bottom
+        } else {
+            Wall::Top
+        }),
+        Cell::Wall => DeathCause::Obstacle,
+        Cell::Hazard(_) => DeathCause::Hazard,
+        Cell::Tail(_) if passes_through_tail => return,
+        // A segment the tail doesn't know of is left over from something that changed the board
+        // behind the snake's back, so it is just in the way.
+        Cell::Tail(segment) => tail
+            .segments
+            .iter()
+            .rev()
+            .position(|&s| s == segment)
+            .map_or(DeathCause::Obstacle, |i| DeathCause::Tail {
+                segment: i + 1,
+            }),
+        _ => return,
+    };
+    died.write(PlayerDied { cause });
+}
+
+fn handle_death(
+    mut died: MessageReader<PlayerDied>,
+    stats: Res<RoundStats>,
+    tail: Res<Tail>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(PlayerDied { cause }) = died.read().last() else {
+        return;
+    };
+    commands.insert_resource(DeathReport {
+        cause: cause.clone(),
+        length: tail.segments.len() + 1,
+        time_survived: stats.time_survived,
+        food_eaten: stats.food_eaten,
+    });
+    next_state.set(GameState::GameOver);
 }
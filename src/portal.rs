@@ -0,0 +1,134 @@
+use crate::board::{self, Board};
+use crate::config::{Config, GameState, HEX_RADIUS, Position, Topology, ZLayer};
+use crate::save::Resuming;
+use crate::settings::{Settings, bounded_world, custom_board};
+use crate::theme::ActiveTheme;
+use crate::{food, player};
+use bevy::prelude::*;
+use rand::prelude::*;
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::InGame),
+            (
+                init.after(board::init),
+                spawn_random_pairs
+                    .run_if(
+                        bounded_world
+                            .and(custom_board)
+                            .and(not(resource_exists::<Resuming>)),
+                    )
+                    .after(init)
+                    .after(player::init)
+                    .after(food::init),
+            ),
+        );
+    }
+}
+
+/// One end of a pair of portals. Both ends of a pair have the same `pair` and color.
+#[derive(Component)]
+pub struct Portal {
+    pub pair: usize,
+}
+
+#[derive(Resource)]
+pub struct PortalAssets {
+    mesh: Handle<Mesh>,
+    /// One material per color of the theme's portal colors.
+    materials: Vec<Handle<ColorMaterial>>,
+    /// How many pairs have been spawned, used to give the next pair its color.
+    pairs: usize,
+}
+
+/// How often to look for a free spot before giving up on a portal.
+const PLACEMENT_ATTEMPTS: usize = 100;
+
+pub fn init(
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let outer = match config.topology {
+        Topology::Square => 0.45,
+        Topology::Hex => HEX_RADIUS * 0.75,
+    } * config.pixels_per_cell as f32;
+    let colors = theme.portals.len().max(1);
+    commands.insert_resource(PortalAssets {
+        mesh: meshes.add(Annulus::new(outer * 0.6, outer)),
+        materials: (0..colors)
+            .map(|i| materials.add(theme.portal_color(i)))
+            .collect(),
+        pairs: 0,
+    });
+}
+
+/// Spawns a pair of portals at `a` and `b`, in the next color.
+pub fn spawn_pair(
+    a: Position,
+    b: Position,
+    board: &mut Board,
+    assets: &mut PortalAssets,
+    commands: &mut Commands,
+) {
+    let pair = assets.pairs;
+    assets.pairs += 1;
+    let material = assets.materials[pair % assets.materials.len()].clone();
+    let mut spawn = |pos: &Position| {
+        commands
+            .spawn((
+                Mesh2d(assets.mesh.clone()),
+                MeshMaterial2d(material.clone()),
+                Portal { pair },
+                pos.clone(),
+                ZLayer { z: 1 },
+            ))
+            .id()
+    };
+    let (a_entity, b_entity) = (spawn(&a), spawn(&b));
+    board.link_portals(&a, a_entity, &b, b_entity);
+}
+
+/// Whether a portal can be put at `pos`. Every cell around it has to be on the board and not be a
+/// portal itself, so that a snake coming out of it always lands on a cell it can enter.
+pub fn can_place(board: &Board, pos: &Position) -> bool {
+    board.topology().dirs().iter().all(|dir| {
+        let next = pos.neighbor(dir, board.topology());
+        board.contains(&next) && !board.is_portal(&next)
+    })
+}
+
+/// A random free cell a portal can be put at, which is neither `partner` nor next to it.
+fn random_place(board: &Board, partner: Option<&Position>, rng: &mut impl Rng) -> Option<Position> {
+    (0..PLACEMENT_ATTEMPTS)
+        .filter_map(|_| board.random_free(IVec2::ZERO, rng))
+        .find(|pos| {
+            can_place(board, pos)
+                && partner.is_none_or(|partner| {
+                    pos != partner && pos.direction_to(partner, board.topology()).is_none()
+                })
+        })
+}
+
+fn spawn_random_pairs(
+    settings: Res<Settings>,
+    mut board: ResMut<Board>,
+    mut assets: ResMut<PortalAssets>,
+    mut commands: Commands,
+) {
+    let mut rng = StdRng::seed_from_u64(getrandom::u64().unwrap_or(0));
+    for _ in 0..settings.portal_pairs {
+        let Some(a) = random_place(&board, None, &mut rng) else {
+            return;
+        };
+        let Some(b) = random_place(&board, Some(&a), &mut rng) else {
+            return;
+        };
+        spawn_pair(a, b, &mut board, &mut assets, &mut commands);
+    }
+}
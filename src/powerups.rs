@@ -0,0 +1,304 @@
+use crate::board::{Board, Cell};
+use crate::config::{Config, GameState, Position, ZLayer, on_tick};
+use crate::player::{Player, SnakeSystems};
+use crate::settings::{Settings, custom_board};
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
+
+mod ghost;
+mod magnet;
+mod reverse;
+mod shield;
+mod slow_motion;
+
+pub struct PowerUpsPlugin;
+
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PowerUpCollected>()
+            .add_plugins((
+                ghost::GhostPlugin,
+                slow_motion::SlowMotionPlugin,
+                magnet::MagnetPlugin,
+                shield::ShieldPlugin,
+                reverse::ReversePlugin,
+            ))
+            .add_systems(OnEnter(GameState::InGame), init)
+            .add_systems(
+                Update,
+                (
+                    // Pickups would spoil boards that are meant to play out the same every time.
+                    spawn_pickups.run_if(custom_board),
+                    expire_pickups,
+                    collect_pickups
+                        .in_set(SnakeSystems::Collide)
+                        .run_if(on_tick),
+                    tick_active,
+                    update_hud.after(tick_active),
+                )
+                    .run_if(in_state(GameState::InGame)),
+            );
+    }
+}
+
+/// A temporary effect the snake gets by picking it up from the board.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PowerUp {
+    /// The snake can pass through its own tail.
+    Ghost,
+    /// The snake moves at half speed.
+    SlowMotion,
+    /// Nearby food moves towards the head.
+    Magnet,
+    /// The snake stops in front of the next thing it would die from instead.
+    Shield,
+    /// The head and the end of the tail swap places.
+    Reverse,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 5] = [
+        PowerUp::Ghost,
+        PowerUp::SlowMotion,
+        PowerUp::Magnet,
+        PowerUp::Shield,
+        PowerUp::Reverse,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUp::Ghost => "Ghost",
+            PowerUp::SlowMotion => "Slow motion",
+            PowerUp::Magnet => "Magnet",
+            PowerUp::Shield => "Shield",
+            PowerUp::Reverse => "Reverse",
+        }
+    }
+
+    /// The letter shown on the pickup.
+    fn symbol(self) -> &'static str {
+        match self {
+            PowerUp::Ghost => "G",
+            PowerUp::SlowMotion => "S",
+            PowerUp::Magnet => "M",
+            PowerUp::Shield => "+",
+            PowerUp::Reverse => "R",
+        }
+    }
+
+    /// How long the effect lasts, or `None` if it happens at once.
+    fn duration(self) -> Option<Duration> {
+        match self {
+            PowerUp::Ghost => Some(Duration::from_secs(5)),
+            PowerUp::SlowMotion => Some(Duration::from_secs(6)),
+            PowerUp::Magnet => Some(Duration::from_secs(8)),
+            PowerUp::Shield => Some(Duration::from_secs(15)),
+            PowerUp::Reverse => None,
+        }
+    }
+}
+
+/// Written when the snake picks up a power-up.
+#[derive(Message)]
+pub struct PowerUpCollected(pub PowerUp);
+
+/// The power-ups in effect and the time left for each.
+#[derive(Default, Resource)]
+pub struct ActivePowerUps(HashMap<PowerUp, Timer>);
+
+impl ActivePowerUps {
+    pub fn is_active(&self, power_up: PowerUp) -> bool {
+        self.0.contains_key(&power_up)
+    }
+
+    /// Whether no power-up is in effect.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Ends the effect of `power_up` early.
+    pub fn end(&mut self, power_up: PowerUp) {
+        self.0.remove(&power_up);
+    }
+}
+
+/// A power-up lying on the board.
+#[derive(Component)]
+struct Pickup {
+    power_up: PowerUp,
+    /// Time until the pickup disappears again.
+    lifetime: Timer,
+}
+
+#[derive(Resource)]
+struct PickupSpawner {
+    timer: Timer,
+    rng: StdRng,
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+    font: Handle<Font>,
+    symbol_color: Color,
+}
+
+/// Marker for the text listing the active power-ups.
+#[derive(Component)]
+struct PowerUpHud;
+
+/// How often a new power-up appears, if there is none on the board.
+const SPAWN_INTERVAL: f32 = 12.0;
+
+/// How long a power-up stays on the board.
+const PICKUP_LIFETIME: f32 = 8.0;
+
+fn init(
+    config: Res<Config>,
+    theme: Res<ActiveTheme>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ActivePowerUps::default());
+    commands.insert_resource(PickupSpawner {
+        timer: Timer::from_seconds(SPAWN_INTERVAL, TimerMode::Repeating),
+        rng: StdRng::seed_from_u64(getrandom::u64().unwrap_or(0)),
+        mesh: meshes.add(RegularPolygon::new(config.pixels_per_cell as f32 * 0.5, 4)),
+        material: materials.add(theme.highlight),
+        font: theme.font.clone(),
+        symbol_color: theme.background,
+    });
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: theme.font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(theme.highlight),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(50.0),
+            top: Val::Px(45.0),
+            ..default()
+        },
+        PowerUpHud,
+    ));
+}
+
+fn spawn_pickups(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    head: Query<&Position, With<Player>>,
+    pickups: Query<(), With<Pickup>>,
+    mut spawner: ResMut<PickupSpawner>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    if !settings.power_ups
+        || !spawner.timer.tick(time.delta()).just_finished()
+        || !pickups.is_empty()
+    {
+        return;
+    }
+    let Ok(head) = head.single() else {
+        return;
+    };
+    let spawner = spawner.as_mut();
+    let chunk = board.chunk_of(head);
+    let Some(pos) = board.random_free(chunk, &mut spawner.rng) else {
+        return;
+    };
+    let power_up = *PowerUp::ALL.choose(&mut spawner.rng).unwrap();
+    let pickup = commands
+        .spawn((
+            Mesh2d(spawner.mesh.clone()),
+            MeshMaterial2d(spawner.material.clone()),
+            Pickup {
+                power_up,
+                lifetime: Timer::from_seconds(PICKUP_LIFETIME, TimerMode::Once),
+            },
+            pos.clone(),
+            ZLayer { z: 2 },
+            children![(
+                Text2d::new(power_up.symbol()),
+                TextFont {
+                    font: spawner.font.clone(),
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(spawner.symbol_color),
+                Transform::from_xyz(0.0, 0.0, 0.1),
+            )],
+        ))
+        .id();
+    board.set(&pos, Cell::PowerUp(pickup));
+}
+
+fn expire_pickups(
+    time: Res<Time>,
+    mut pickups: Query<(Entity, &mut Pickup, &Position)>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    for (entity, mut pickup, pos) in pickups.iter_mut() {
+        if pickup.lifetime.tick(time.delta()).just_finished() {
+            board.vacate(pos, entity);
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn collect_pickups(
+    head: Query<&Position, With<Player>>,
+    pickups: Query<&Pickup>,
+    board: Res<Board>,
+    mut active: ResMut<ActivePowerUps>,
+    mut collected: MessageWriter<PowerUpCollected>,
+    mut commands: Commands,
+) {
+    let Ok(head) = head.single() else {
+        return;
+    };
+    let Cell::PowerUp(entity) = board.at(head) else {
+        return;
+    };
+    let Ok(pickup) = pickups.get(entity) else {
+        return;
+    };
+    if let Some(duration) = pickup.power_up.duration() {
+        // Picking up an active power-up again starts its time over.
+        active
+            .0
+            .insert(pickup.power_up, Timer::new(duration, TimerMode::Once));
+    }
+    collected.write(PowerUpCollected(pickup.power_up));
+    commands.entity(entity).despawn();
+}
+
+fn tick_active(time: Res<Time>, mut active: ResMut<ActivePowerUps>) {
+    active
+        .0
+        .retain(|_, timer| !timer.tick(time.delta()).is_finished());
+}
+
+fn update_hud(active: Res<ActivePowerUps>, mut hud: Query<&mut Text, With<PowerUpHud>>) {
+    if !active.is_changed() {
+        return;
+    }
+    let lines: Vec<String> = PowerUp::ALL
+        .into_iter()
+        .filter_map(|power_up| {
+            let timer = active.0.get(&power_up)?;
+            Some(format!(
+                "{} {:.1}s",
+                power_up.name(),
+                timer.remaining_secs()
+            ))
+        })
+        .collect();
+    for mut text in hud.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
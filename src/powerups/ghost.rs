@@ -0,0 +1,32 @@
+use super::{ActivePowerUps, PowerUp};
+use crate::config::GameState;
+use crate::player::{PassesThroughTail, Player};
+use bevy::prelude::*;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_ghost.run_if(in_state(GameState::InGame)));
+    }
+}
+
+/// Lets the snake pass through its own tail while the power-up is active.
+fn update_ghost(
+    active: Res<ActivePowerUps>,
+    head: Query<(Entity, Has<PassesThroughTail>), With<Player>>,
+    mut commands: Commands,
+) {
+    let Ok((head, passes_through_tail)) = head.single() else {
+        return;
+    };
+    match (active.is_active(PowerUp::Ghost), passes_through_tail) {
+        (true, false) => {
+            commands.entity(head).insert(PassesThroughTail);
+        }
+        (false, true) => {
+            commands.entity(head).remove::<PassesThroughTail>();
+        }
+        _ => {}
+    }
+}
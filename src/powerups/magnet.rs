@@ -0,0 +1,89 @@
+use super::{ActivePowerUps, PowerUp};
+use crate::board::{Board, Cell};
+use crate::config::{Config, GameState, Position, PreviousPosition, on_tick};
+use crate::food::Food;
+use crate::player::{Player, SnakeSystems};
+use bevy::prelude::*;
+
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            pull_food
+                .after(SnakeSystems::Collide)
+                .run_if(in_state(GameState::InGame).and(on_tick)),
+        );
+    }
+}
+
+/// How close food has to be to the head to be pulled, in cells.
+const RANGE: f32 = 5.0;
+
+type FoodQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Position,
+        Option<&'static mut PreviousPosition>,
+    ),
+    (With<Food>, Without<Player>),
+>;
+
+/// Moves every food within range one cell closer to the head on each tick.
+fn pull_food(
+    active: Res<ActivePowerUps>,
+    config: Res<Config>,
+    head: Query<&Position, With<Player>>,
+    mut food: FoodQuery,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    // Food only slides on the tick it was pulled, and rests wherever it isn't pulled any further.
+    for (_, pos, prev) in food.iter_mut() {
+        if let Some(mut prev) = prev
+            && prev.0 != *pos
+        {
+            prev.0 = pos.clone();
+        }
+    }
+    if !active.is_active(PowerUp::Magnet) {
+        return;
+    }
+    let Ok(head) = head.single() else {
+        return;
+    };
+    let target = config.cell_center(head);
+    let range = RANGE * config.pixels_per_cell as f32;
+    for (entity, mut pos, prev) in food.iter_mut() {
+        let distance = config.cell_center(&pos).distance(target);
+        if distance > range {
+            continue;
+        }
+        let closer = board
+            .topology()
+            .dirs()
+            .iter()
+            .map(|dir| pos.neighbor(dir, board.topology()))
+            .filter(|next| next != head && board.at(next) == Cell::Empty)
+            .map(|next| (config.cell_center(&next).distance(target), next))
+            .filter(|(next_distance, _)| *next_distance < distance)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+        let Some((_, next)) = closer else {
+            continue;
+        };
+        board.vacate(&pos, entity);
+        board.set(&next, Cell::Food(entity));
+        match prev {
+            Some(mut prev) => prev.0 = pos.clone(),
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(PreviousPosition(pos.clone()));
+            }
+        }
+        *pos = next;
+    }
+}
@@ -0,0 +1,104 @@
+use super::{PowerUp, PowerUpCollected, collect_pickups};
+use crate::board::{Board, Cell};
+use crate::config::{GameState, Position, PreviousPosition, Velocity};
+use crate::player::{Player, SnakeSystems, Tail, TailSegment};
+use bevy::prelude::*;
+
+pub struct ReversePlugin;
+
+impl Plugin for ReversePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            reverse_snake
+                .in_set(SnakeSystems::Collide)
+                .after(collect_pickups)
+                .run_if(in_state(GameState::InGame).and(on_message::<PowerUpCollected>)),
+        );
+    }
+}
+
+/// Swaps the head with the end of the tail and turns the snake around, so that it heads away from
+/// where it was going.
+fn reverse_snake(
+    mut collected: MessageReader<PowerUpCollected>,
+    mut tail: ResMut<Tail>,
+    mut board: ResMut<Board>,
+    mut head: Query<(Entity, &mut Position, &mut PreviousPosition, &mut Velocity), With<Player>>,
+    mut segments: Query<&mut Position, (With<TailSegment>, Without<Player>)>,
+    mut commands: Commands,
+) {
+    if !collected
+        .read()
+        .any(|PowerUpCollected(power_up)| *power_up == PowerUp::Reverse)
+    {
+        return;
+    }
+    let Ok((head, mut head_pos, mut head_prev, mut velocity)) = head.single_mut() else {
+        return;
+    };
+    let Some(end) = tail.end() else {
+        velocity.dir = velocity.dir.opposite();
+        return;
+    };
+    let Ok(mut end_pos) = segments.get_mut(end) else {
+        return;
+    };
+    std::mem::swap(head_pos.as_mut(), end_pos.as_mut());
+    board.set(&head_pos, Cell::Head(head));
+    board.set(&end_pos, Cell::Tail(end));
+    head_prev.0 = head_pos.clone();
+    commands.entity(end).remove::<PreviousPosition>();
+    tail.turn_around();
+
+    // Head away from the segment that is now right behind the head.
+    let behind = tail
+        .newest()
+        .and_then(|segment| segments.get(segment).ok())
+        .cloned();
+    velocity.dir = behind
+        .and_then(|behind| board.direction(&behind, &head_pos))
+        .unwrap_or_else(|| velocity.dir.opposite());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Dir, Topology};
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn reversing_keeps_the_snake_connected() {
+        let mut app = App::new();
+        app.add_message::<PowerUpCollected>()
+            .insert_resource(Board::bounded(10, 10, Topology::Square));
+        let at = |x| Position { x, y: 0 };
+        let world = app.world_mut();
+        let head = world
+            .spawn((
+                Player,
+                at(4),
+                PreviousPosition(at(3)),
+                Velocity { dir: Dir::Right },
+            ))
+            .id();
+        let segments: Vec<Entity> = (1..=3)
+            .map(|x| world.spawn((TailSegment, at(x))).id())
+            .collect();
+        world.insert_resource(segments.iter().copied().collect::<Tail>());
+        world.write_message(PowerUpCollected(PowerUp::Reverse));
+        world.run_system_once(reverse_snake).unwrap();
+
+        // The end of the tail took the place of the head and the rest of the tail follows the
+        // head the other way round.
+        let tail: Vec<Entity> = world.resource::<Tail>().iter().collect();
+        assert!(tail == [segments[0], segments[2], segments[1]]);
+        let pos = |entity| world.get::<Position>(entity).unwrap().clone();
+        assert!(pos(head) == at(1));
+        assert!(tail.iter().map(|&s| pos(s)).eq([at(4), at(3), at(2)]));
+        assert!(world.get::<Velocity>(head).unwrap().dir == Dir::Left);
+        let board = world.resource::<Board>();
+        assert!(board.at(&at(1)) == Cell::Head(head));
+        assert!(board.at(&at(4)) == Cell::Tail(segments[0]));
+    }
+}
@@ -0,0 +1,73 @@
+use super::{ActivePowerUps, PowerUp};
+use crate::board::{Board, Cell};
+use crate::config::{GameState, Position, Velocity, on_tick};
+use crate::hazard::MoveHazards;
+use crate::player::{HoldsStill, PassesThroughTail, Player, SegmentsToGrow, SnakeSystems, Tail};
+use bevy::prelude::*;
+
+pub struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            stop_before_collision
+                .after(SnakeSystems::Tick)
+                .after(MoveHazards)
+                .before(SnakeSystems::Move)
+                .run_if(in_state(GameState::InGame).and(on_tick)),
+        );
+    }
+}
+
+impl ActivePowerUps {
+    /// Uses up the shield, if it is active, to save the snake from something that would kill it.
+    /// Returns whether the snake was saved.
+    pub fn use_shield(&mut self) -> bool {
+        let shielded = self.is_active(PowerUp::Shield);
+        self.end(PowerUp::Shield);
+        shielded
+    }
+}
+
+type HeadQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Position,
+        &'static Velocity,
+        &'static SegmentsToGrow,
+        Has<PassesThroughTail>,
+    ),
+    With<Player>,
+>;
+
+/// Holds the snake still for a tick if its next move would kill it, using up the shield. This
+/// leaves the player a tick to steer out of the way.
+fn stop_before_collision(
+    tail: Res<Tail>,
+    board: Res<Board>,
+    mut active: ResMut<ActivePowerUps>,
+    head: HeadQuery,
+    mut commands: Commands,
+) {
+    if !active.is_active(PowerUp::Shield) {
+        return;
+    }
+    let Ok((entity, pos, velocity, to_grow, passes_through_tail)) = head.single() else {
+        return;
+    };
+    let next = board.neighbor(pos, &velocity.dir);
+    let deadly = match board.at(&next) {
+        Cell::Wall | Cell::Hazard(_) => true,
+        // The end of the tail moves out of the way unless the snake is growing.
+        Cell::Tail(segment) => {
+            !passes_through_tail && (Some(segment) != tail.end() || to_grow.0 > 0)
+        }
+        _ => false,
+    };
+    if deadly && active.use_shield() {
+        commands.entity(entity).insert(HoldsStill);
+    }
+}
@@ -0,0 +1,44 @@
+use super::{ActivePowerUps, PowerUp};
+use crate::config::{GameState, TickTimer};
+use bevy::prelude::*;
+
+pub struct SlowMotionPlugin;
+
+impl Plugin for SlowMotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), init)
+            .add_systems(
+                Update,
+                update_slow_motion.run_if(in_state(GameState::InGame)),
+            );
+    }
+}
+
+/// How many times longer each tick takes in slow motion.
+const SLOWDOWN: u32 = 2;
+
+/// Whether the [`TickTimer`] is currently slowed down.
+#[derive(Resource)]
+struct Slowed(bool);
+
+fn init(mut commands: Commands) {
+    commands.insert_resource(Slowed(false));
+}
+
+fn update_slow_motion(
+    active: Res<ActivePowerUps>,
+    mut slowed: ResMut<Slowed>,
+    mut timer: ResMut<TickTimer>,
+) {
+    let slow = active.is_active(PowerUp::SlowMotion);
+    if slow == slowed.0 {
+        return;
+    }
+    let interval = timer.0.duration();
+    timer.0.set_duration(if slow {
+        interval * SLOWDOWN
+    } else {
+        interval / SLOWDOWN
+    });
+    slowed.0 = slow;
+}
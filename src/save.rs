@@ -0,0 +1,442 @@
+//! Saving the round in progress when quitting, to resume it later from the settings screen.
+//!
+//! Only endless rounds and rounds of the custom level can be saved, and only on a bounded board:
+//! the other game modes are timed or scored, and an infinite world is generated as the snake
+//! explores it. Hazards, random portals and power-ups in effect are not part of a saved round, so
+//! rounds with any of them can't be saved. The portals of the custom level are always the same and
+//! are placed again.
+
+use crate::actions::{Action, Actions, key_name};
+use crate::board::{Board, Cell};
+use crate::config::{
+    Config, Dir, GameState, Position, PreviousPosition, TickTimer, Topology, Velocity,
+};
+use crate::food::{self, Food, FoodAssets, FoodRng, FoodSpawnTimer};
+use crate::hazard::Danger;
+use crate::level::CustomLevel;
+use crate::player::{self, Player, PlayerAssets, RoundStats, SegmentsToGrow, Tail, TailSegment};
+use crate::portal::Portal;
+use crate::powerups::ActivePowerUps;
+use crate::score::ScoreValue;
+use crate::settings::{self, GameMode, Settings, WorldMode};
+use crate::storage;
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(saved) = storage::load::<SavedGame>(SAVE_KEY) {
+            app.insert_resource(saved);
+        }
+        app.configure_sets(
+            OnEnter(GameState::InGame),
+            RestoreRound.after(player::init).after(food::init),
+        )
+        .add_systems(
+            OnEnter(GameState::InGame),
+            (restore_snake, restore_food, restore_round)
+                .chain()
+                .in_set(RestoreRound)
+                .run_if(resource_exists::<Resuming>),
+        )
+        .add_systems(OnExit(GameState::InGame), finish_resuming)
+        .add_systems(
+            OnEnter(GameState::Settings),
+            show_resume_hint.run_if(resource_exists::<SavedGame>),
+        )
+        .add_systems(
+            Update,
+            resume_saved_game
+                .run_if(in_state(GameState::Settings).and(resource_exists::<SavedGame>)),
+        )
+        .add_systems(
+            Update,
+            (
+                offer_save.run_if(offers_save),
+                answer_quit_prompt.run_if(resource_exists::<QuitPrompt>),
+                (save_snake, save_food, save_round)
+                    .chain()
+                    .run_if(resource_exists::<Saving>),
+            )
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+const SAVE_KEY: &str = "saved_game";
+
+/// Restores a saved round once the board, the snake and the food are set up.
+#[derive(SystemSet, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RestoreRound;
+
+/// A round saved when quitting, stored until it is resumed.
+#[derive(Resource, Deserialize, Serialize)]
+struct SavedGame {
+    mode: GameMode,
+    board_size: i32,
+    topology: Topology,
+    /// The rows of the custom level the round is played on. Empty for endless rounds.
+    level: Vec<String>,
+    head: Position,
+    dir: Dir,
+    segments_to_grow: u32,
+    /// Where the tail segments are, oldest first.
+    tail: Vec<Position>,
+    food: Vec<Position>,
+    score: i32,
+    /// How far the current tick has progressed. The interval itself is not saved, as it is only
+    /// ever changed by slow motion, which is never in effect in a saved round.
+    tick_elapsed: Duration,
+    food_timer: Timer,
+    time_survived: Duration,
+    food_eaten: u32,
+    /// The state of [`FoodRng`], so that food keeps appearing where it would have.
+    food_rng: ChaCha12Rng,
+}
+
+impl SavedGame {
+    /// A saved round for the game mode and board in `settings`, still without the snake and food.
+    fn new(settings: &Settings, level: &CustomLevel) -> SavedGame {
+        SavedGame {
+            mode: settings.game_mode,
+            board_size: settings.board_size,
+            topology: settings.topology,
+            level: match settings.game_mode {
+                GameMode::Custom => level.0.rows.clone(),
+                _ => Vec::new(),
+            },
+            head: Position { x: 0, y: 0 },
+            dir: Dir::None,
+            segments_to_grow: 0,
+            tail: Vec::new(),
+            food: Vec::new(),
+            score: 0,
+            tick_elapsed: Duration::ZERO,
+            food_timer: Timer::default(),
+            time_survived: Duration::ZERO,
+            food_eaten: 0,
+            food_rng: ChaCha12Rng::seed_from_u64(0),
+        }
+    }
+
+    /// Whether the round can still be played. A round on the custom level can't once the level
+    /// was changed in the editor.
+    fn resumable(&self, level: &CustomLevel) -> bool {
+        self.mode != GameMode::Custom || self.level == level.0.rows
+    }
+}
+
+/// Inserted while the saved round is played, from resuming it until the round ends.
+#[derive(Resource)]
+pub struct Resuming;
+
+fn finish_resuming(mut commands: Commands) {
+    commands.remove_resource::<Resuming>();
+}
+
+/// Inserted while the round is being saved, just before quitting.
+#[derive(Resource)]
+struct Saving;
+
+/// Inserted while the game asks whether to save the round before quitting.
+#[derive(Resource)]
+pub struct QuitPrompt {
+    /// Whether the game was paused already, to keep it paused when the player keeps playing.
+    was_paused: bool,
+}
+
+/// Marker for the question asked by [`QuitPrompt`].
+#[derive(Component)]
+struct QuitPromptText;
+
+/// Run condition for quitting to ask whether to save the round first, instead of quitting right
+/// away.
+pub fn offers_save(
+    state: Res<State<GameState>>,
+    settings: Res<Settings>,
+    power_ups: Option<Res<ActivePowerUps>>,
+    hazards: Query<(), With<Danger>>,
+    portals: Query<(), With<Portal>>,
+) -> bool {
+    // The portals of endless rounds are placed at random, those of the custom level are not.
+    let random_portals = settings.game_mode == GameMode::Endless && !portals.is_empty();
+    *state.get() == GameState::InGame
+        && settings.world() == WorldMode::Bounded
+        && matches!(settings.game_mode, GameMode::Endless | GameMode::Custom)
+        && hazards.is_empty()
+        && !random_portals
+        && power_ups.is_none_or(|power_ups| power_ups.is_empty())
+}
+
+/// Pauses the game and asks whether to save when quitting, or takes the question back when
+/// quitting again.
+fn offer_save(
+    actions: Res<Actions>,
+    settings: Res<Settings>,
+    theme: Res<ActiveTheme>,
+    prompt: Option<Res<QuitPrompt>>,
+    prompt_text: Query<Entity, With<QuitPromptText>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::Quit) {
+        return;
+    }
+    if let Some(prompt) = prompt {
+        if !prompt.was_paused {
+            time.unpause();
+        }
+        for entity in prompt_text.iter() {
+            commands.entity(entity).despawn();
+        }
+        commands.remove_resource::<QuitPrompt>();
+        return;
+    }
+    commands.insert_resource(QuitPrompt {
+        was_paused: time.is_paused(),
+    });
+    time.pause();
+    let key = |action: Action| {
+        settings
+            .key_bindings
+            .key(action)
+            .map_or_else(|| action.name().to_owned(), key_name)
+    };
+    let (save_key, discard_key, quit_key) =
+        (key(Action::Restart), key(Action::Pause), key(Action::Quit));
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        QuitPromptText,
+        children![(
+            Node {
+                padding: UiRect::all(Val::Px(30.0)),
+                ..default()
+            },
+            BackgroundColor(theme.background),
+            children![(
+                Text::new(format!(
+                    "Save the game before quitting?\n\
+                     {save_key}: save and quit   {discard_key}: quit without saving   \
+                     {quit_key}: keep playing"
+                )),
+                TextFont {
+                    font: theme.font.clone(),
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(theme.highlight),
+                TextLayout::new_with_justify(Justify::Center),
+            )],
+        )],
+    ));
+}
+
+/// Saves and quits on [`Action::Restart`], which is also what tapping the screen does, and quits
+/// without saving on [`Action::Pause`].
+fn answer_quit_prompt(
+    actions: Res<Actions>,
+    settings: Res<Settings>,
+    level: Res<CustomLevel>,
+    focused_windows: Query<(Entity, &Window)>,
+    mut commands: Commands,
+) {
+    if actions.just_pressed(Action::Restart) {
+        commands.insert_resource(SavedGame::new(&settings, &level));
+        commands.insert_resource(Saving);
+    } else if actions.just_pressed(Action::Pause) {
+        crate::close_focused_window(&mut commands, &focused_windows);
+    }
+}
+
+fn save_snake(
+    player: Query<(&Position, &Velocity, &SegmentsToGrow), With<Player>>,
+    segments: Query<&Position, With<TailSegment>>,
+    tail: Res<Tail>,
+    mut saved: ResMut<SavedGame>,
+) {
+    let Ok((head, velocity, to_grow)) = player.single() else {
+        return;
+    };
+    saved.head = head.clone();
+    saved.dir = velocity.dir.clone();
+    saved.segments_to_grow = to_grow.0;
+    saved.tail = tail
+        .iter()
+        .filter_map(|segment| segments.get(segment).ok().cloned())
+        .collect();
+}
+
+fn save_food(
+    food: Query<&Position, With<Food>>,
+    timer: Res<FoodSpawnTimer>,
+    rng: Res<FoodRng>,
+    mut saved: ResMut<SavedGame>,
+) {
+    saved.food = food.iter().cloned().collect();
+    saved.food_timer = timer.0.clone();
+    saved.food_rng = rng.0.clone();
+}
+
+/// Stores the round and quits.
+fn save_round(
+    score: Res<ScoreValue>,
+    tick_timer: Res<TickTimer>,
+    stats: Res<RoundStats>,
+    focused_windows: Query<(Entity, &Window)>,
+    mut saved: ResMut<SavedGame>,
+    mut commands: Commands,
+) {
+    saved.score = score.0;
+    saved.tick_elapsed = tick_timer.0.elapsed();
+    saved.time_survived = stats.time_survived;
+    saved.food_eaten = stats.food_eaten;
+    storage::save(SAVE_KEY, saved.as_ref());
+    crate::close_focused_window(&mut commands, &focused_windows);
+}
+
+fn show_resume_hint(
+    saved: Res<SavedGame>,
+    level: Res<CustomLevel>,
+    theme: Res<ActiveTheme>,
+    mut commands: Commands,
+) {
+    let hint = if saved.resumable(&level) {
+        format!("R: resume the saved {} round", saved.mode.name())
+    } else {
+        "The custom level was changed since the round was saved, so it can't be resumed".to_owned()
+    };
+    commands.spawn((
+        Text::new(hint),
+        TextFont {
+            font: theme.font.clone(),
+            font_size: 25.0,
+            ..default()
+        },
+        TextColor(theme.highlight),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.0),
+            bottom: Val::Px(5.0),
+            ..default()
+        },
+    ));
+}
+
+/// Starts the saved round with the game mode and board it was played with.
+fn resume_saved_game(
+    keys: Res<ButtonInput<KeyCode>>,
+    saved: Res<SavedGame>,
+    level: Res<CustomLevel>,
+    mut settings: ResMut<Settings>,
+    mut config: ResMut<Config>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    if !keys.just_pressed(KeyCode::KeyR) || !saved.resumable(&level) {
+        return;
+    }
+    settings.game_mode = saved.mode;
+    if saved.mode == GameMode::Endless {
+        settings.world_mode = WorldMode::Bounded;
+        settings.board_size = saved.board_size;
+        settings.topology = saved.topology;
+    }
+    // The board is set up before the settings screen would notice the change.
+    settings::sync_board_config(&settings, &level, &mut config);
+    commands.insert_resource(Resuming);
+    next_state.set(GameState::InGame);
+}
+
+type SnakeHeadQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Position,
+        &'static mut PreviousPosition,
+        &'static mut Velocity,
+        &'static mut SegmentsToGrow,
+    ),
+    With<Player>,
+>;
+
+fn restore_snake(
+    saved: Res<SavedGame>,
+    assets: Res<PlayerAssets>,
+    mut player: SnakeHeadQuery,
+    mut tail: ResMut<Tail>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    let Ok((head, mut pos, mut prev_pos, mut velocity, mut to_grow)) = player.single_mut() else {
+        return;
+    };
+    board.vacate(&pos, head);
+    *pos = saved.head.clone();
+    prev_pos.0 = saved.head.clone();
+    velocity.dir = saved.dir.clone();
+    to_grow.0 = saved.segments_to_grow;
+    board.set(&pos, Cell::Head(head));
+    player::restore_tail(
+        &saved.tail,
+        &saved.head,
+        &mut tail,
+        &mut board,
+        &mut commands,
+        &assets,
+    );
+}
+
+/// Replaces the food placed for a new round with the saved food.
+fn restore_food(
+    saved: Res<SavedGame>,
+    assets: Res<FoodAssets>,
+    food: Query<(Entity, &Position), With<Food>>,
+    mut timer: ResMut<FoodSpawnTimer>,
+    mut rng: ResMut<FoodRng>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    for (entity, pos) in food.iter() {
+        board.vacate(pos, entity);
+        commands.entity(entity).despawn();
+    }
+    for pos in &saved.food {
+        if board.at(pos) == Cell::Empty {
+            let food = food::spawn_at(&mut commands, &assets, pos.clone());
+            board.set(pos, Cell::Food(food));
+        }
+    }
+    timer.0 = saved.food_timer.clone();
+    rng.0 = saved.food_rng.clone();
+}
+
+/// Restores the score and the timers, and forgets the saved round so it can't be resumed twice.
+fn restore_round(
+    saved: Res<SavedGame>,
+    mut score: ResMut<ScoreValue>,
+    mut tick_timer: ResMut<TickTimer>,
+    mut stats: ResMut<RoundStats>,
+    mut commands: Commands,
+) {
+    score.0 = saved.score;
+    tick_timer.0.set_elapsed(saved.tick_elapsed);
+    stats.time_survived = saved.time_survived;
+    stats.food_eaten = saved.food_eaten;
+    commands.remove_resource::<SavedGame>();
+    storage::remove(SAVE_KEY);
+}
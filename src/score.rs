@@ -1,37 +1,60 @@
-use crate::config::GameState;
+use crate::config::{GameState, Position};
+use crate::theme::ActiveTheme;
 use bevy::prelude::*;
 
 pub struct ScorePlugin;
 
 #[derive(Message)]
 pub enum ScoreUpdate {
-    AteFood,
+    AteFood {
+        position: Position,
+    },
+    /// The snake got one cell further away from where it started than ever before.
+    Explored,
 }
 
+impl ScoreUpdate {
+    /// How many points this update is worth.
+    pub fn points(&self) -> i32 {
+        match self {
+            ScoreUpdate::AteFood { .. } => 1,
+            ScoreUpdate::Explored => 1,
+        }
+    }
+}
+
+/// Written when the score reaches the next level.
+#[derive(Message)]
+pub struct LevelUp;
+
+/// The score needed to advance by one level.
+const POINTS_PER_LEVEL: i32 = 10;
+
 #[derive(Resource)]
-struct ScoreValue(i32);
+pub struct ScoreValue(pub i32);
 
 impl Plugin for ScorePlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<ScoreUpdate>()
+            .add_message::<LevelUp>()
             .add_systems(OnEnter(GameState::InGame), init)
-            .add_systems(Update, update);
+            .add_systems(Update, update.run_if(in_state(GameState::InGame)));
     }
 }
 
 #[derive(Component)]
 struct Score;
 
-fn init(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn init(mut commands: Commands, theme: Res<ActiveTheme>) {
     commands.insert_resource(ScoreValue(0));
     commands.spawn((
         Text::new(""), // Updated later
         TextFont {
-            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font: theme.font.clone(),
             font_size: 30.0,
             ..default()
         },
-        TextColor(Color::WHITE),
+        TextColor(theme.text),
         TextLayout::new_with_justify(Justify::Left),
         Node {
             align_self: AlignSelf::FlexEnd,
@@ -48,10 +71,18 @@ fn update(
     mut score: ResMut<ScoreValue>,
     mut query: Query<&mut Text, With<Score>>,
     mut event: MessageReader<ScoreUpdate>,
+    mut level_up: MessageWriter<LevelUp>,
 ) {
     let mut text = query.single_mut().unwrap();
-    for _ in event.read() {
-        score.0 += 1;
+    for update in event.read() {
+        score.0 += update.points();
+        if score.0 % POINTS_PER_LEVEL == 0 {
+            level_up.write(LevelUp);
+        }
     }
-    text.0 = format!("Score: {}", score.0);
+    text.0 = format!(
+        "Score: {}   Level: {}",
+        score.0,
+        score.0 / POINTS_PER_LEVEL + 1
+    );
 }
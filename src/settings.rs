@@ -0,0 +1,819 @@
+use crate::actions::{Action, Actions, KeyBindings, Preset, Rebinding, key_name};
+use crate::campaign::{self, LEVELS};
+use crate::config::{Config, Dir, GameState, Topology};
+use crate::daily;
+use crate::level::{self, CustomLevel};
+use crate::storage;
+use crate::theme::{ActiveTheme, ThemeKind};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(
+            storage::load::<Settings>(SETTINGS_KEY)
+                .unwrap_or_default()
+                .clamped(),
+        )
+        .add_message::<MenuNavigated>()
+        .add_systems(OnEnter(GameState::Settings), show_settings_screen)
+        .add_systems(
+            Update,
+            (
+                read_settings_input.before(crate::theme::apply_theme),
+                update_settings_screen.after(crate::theme::apply_theme),
+            )
+                .run_if(in_state(GameState::Settings)),
+        )
+        .add_systems(Update, (save_settings, apply_board_settings));
+    }
+}
+
+const SETTINGS_KEY: &str = "settings";
+
+/// The range of [`Settings::input_buffer_size`].
+const INPUT_BUFFER_SIZES: (u8, u8) = (1, 5);
+
+/// The board sizes that can be chosen on the settings screen.
+const BOARD_SIZES: [i32; 6] = [20, 50, 100, 250, 500, 1000];
+
+/// The range of [`Settings::view_cells`].
+const VIEW_CELLS: (u8, u8) = (10, 60);
+
+/// The range of [`Settings::minimap_size`].
+const MINIMAP_SIZES: (u8, u8) = (10, 50);
+
+/// The highest volume level. Volumes are stored in steps so they can be changed with the arrow keys.
+pub const MAX_VOLUME: u8 = 10;
+
+/// User preferences that can be changed on the settings screen.
+#[derive(Resource, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemeKind,
+    /// Volume of sound effects from 0 to [`MAX_VOLUME`].
+    pub sound_volume: u8,
+    /// Volume of the background music from 0 to [`MAX_VOLUME`].
+    pub music_volume: u8,
+    pub muted: bool,
+    /// How far a gamepad stick has to be tilted to steer, in tenths of the full range.
+    pub stick_deadzone: u8,
+    /// Show arrow buttons on the screen for touch devices.
+    pub touch_buttons: bool,
+    pub key_bindings: KeyBindings,
+    pub control_scheme: ControlScheme,
+    /// How many turns can be queued ahead of the snake with [`InputBuffering::Queue`].
+    pub input_buffer_size: u8,
+    pub input_buffering: InputBuffering,
+    /// Ignore a turn that reverses the previously queued turn, as it would be dropped later anyway.
+    pub reject_queued_reversals: bool,
+    pub food_particles: bool,
+    pub death_explosion: bool,
+    /// Show the points gained floating above the food that was eaten.
+    pub score_popups: bool,
+    pub growth_pulse: bool,
+    pub screen_shake: bool,
+    /// Slide the snake from cell to cell instead of moving it a whole cell at once.
+    pub smooth_movement: bool,
+    pub camera_mode: CameraMode,
+    /// How many cells fit across the shorter side of the window with [`CameraMode::FollowHead`].
+    pub view_cells: u8,
+    pub minimap_corner: Corner,
+    /// Size of the longer side of the minimap, in percent of the shorter side of the window.
+    pub minimap_size: u8,
+    pub game_mode: GameMode,
+    /// Index in [`LEVELS`] of the level played with [`GameMode::Campaign`].
+    pub campaign_level: usize,
+    /// Width and height of the board in cells, or of each chunk with [`WorldMode::Infinite`].
+    pub board_size: i32,
+    pub world_mode: WorldMode,
+    pub topology: Topology,
+    /// How many pairs of portals are placed at random on a bounded board.
+    pub portal_pairs: u8,
+    /// How many moving walls, spikes and enemies are placed at random on a bounded board.
+    pub hazards: u8,
+    pub power_ups: bool,
+}
+
+/// What the snake plays for.
+#[derive(Clone, Copy, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum GameMode {
+    /// A single round that lasts until the snake dies.
+    #[default]
+    Endless,
+    /// A sequence of hand-built levels, each with its own objective.
+    Campaign,
+    /// A round against the clock, to eat as much food as possible before it runs out.
+    TimeAttack,
+    /// A round on a board and with rules that are the same for everyone on the same day.
+    Daily,
+    /// A round on the level made in the editor.
+    Custom,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Endless,
+        GameMode::Campaign,
+        GameMode::TimeAttack,
+        GameMode::Daily,
+        GameMode::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Endless => "Endless",
+            GameMode::Campaign => "Campaign",
+            GameMode::TimeAttack => "Time attack",
+            GameMode::Daily => "Daily challenge",
+            GameMode::Custom => "Custom level",
+        }
+    }
+}
+
+/// Run condition for systems that set up the board from the settings rather than from a level or
+/// the daily challenge.
+pub fn custom_board(settings: Res<Settings>) -> bool {
+    matches!(settings.game_mode, GameMode::Endless | GameMode::TimeAttack)
+}
+
+/// The shape of the world the snake moves in.
+#[derive(Clone, Copy, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum WorldMode {
+    /// A single board surrounded by walls.
+    #[default]
+    Bounded,
+    /// Endless, with obstacles and food generated as the snake explores.
+    Infinite,
+}
+
+/// Run condition for systems that only apply to a bounded board.
+pub fn bounded_world(settings: Res<Settings>) -> bool {
+    settings.world() == WorldMode::Bounded
+}
+
+impl WorldMode {
+    pub const ALL: [WorldMode; 2] = [WorldMode::Bounded, WorldMode::Infinite];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WorldMode::Bounded => "Bounded",
+            WorldMode::Infinite => "Infinite",
+        }
+    }
+}
+
+impl Settings {
+    /// Brings values that the settings screen keeps in range back into it, for settings that were
+    /// stored by hand or by an older version.
+    fn clamped(mut self) -> Settings {
+        let (min, max) = INPUT_BUFFER_SIZES;
+        self.input_buffer_size = self.input_buffer_size.clamp(min, max);
+        // A board of no cells can't be played on, so other sizes snap to the closest one offered.
+        self.board_size = BOARD_SIZES
+            .into_iter()
+            .min_by_key(|size| size.abs_diff(self.board_size))
+            .unwrap();
+        let (min, max) = VIEW_CELLS;
+        self.view_cells = self.view_cells.clamp(min, max);
+        let (min, max) = MINIMAP_SIZES;
+        self.minimap_size = self.minimap_size.clamp(min, max);
+        self
+    }
+
+    /// The world that is played in. Campaign levels are always bounded.
+    pub fn world(&self) -> WorldMode {
+        match self.game_mode {
+            GameMode::Endless | GameMode::TimeAttack => self.world_mode,
+            GameMode::Campaign | GameMode::Daily | GameMode::Custom => WorldMode::Bounded,
+        }
+    }
+
+    /// Zooms the camera out by `delta` steps, or in if `delta` is negative.
+    pub fn change_view_cells(&mut self, delta: i32) {
+        let (min, max) = VIEW_CELLS;
+        self.view_cells = (self.view_cells as i32 + 5 * delta).clamp(min as i32, max as i32) as u8;
+    }
+
+    /// Whether the camera follows the head. An infinite world can't be shown whole, so it always
+    /// does there.
+    pub fn camera_follows_head(&self) -> bool {
+        self.camera_mode == CameraMode::FollowHead || self.world() == WorldMode::Infinite
+    }
+}
+
+/// A corner of the window to show an overlay in, or `Off` to hide it.
+#[derive(Clone, Copy, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Corner {
+    Off,
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 5] = [
+        Corner::Off,
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Corner::Off => "Off",
+            Corner::TopLeft => "Top left",
+            Corner::TopRight => "Top right",
+            Corner::BottomLeft => "Bottom left",
+            Corner::BottomRight => "Bottom right",
+        }
+    }
+}
+
+/// What the camera shows.
+#[derive(Clone, Copy, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum CameraMode {
+    /// The whole board, scaled to fit the window.
+    #[default]
+    FitBoard,
+    /// The area around the head, zoomed in and following it.
+    FollowHead,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 2] = [CameraMode::FitBoard, CameraMode::FollowHead];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::FitBoard => "Whole board",
+            CameraMode::FollowHead => "Follow head",
+        }
+    }
+}
+
+/// What happens to turns pressed faster than the snake moves.
+#[derive(Clone, Copy, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum InputBuffering {
+    /// Turns are applied one per move, in order, up to the buffer size.
+    #[default]
+    Queue,
+    /// Only the last turn pressed before a move is applied.
+    LatestWins,
+}
+
+impl InputBuffering {
+    pub const ALL: [InputBuffering; 2] = [InputBuffering::Queue, InputBuffering::LatestWins];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputBuffering::Queue => "Queue",
+            InputBuffering::LatestWins => "Latest wins",
+        }
+    }
+}
+
+/// How the turn actions steer the snake.
+#[derive(Clone, Copy, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ControlScheme {
+    /// Each turn action steers to its own direction on the screen.
+    #[default]
+    Absolute,
+    /// Only left and right are used, turning relative to the direction the snake is heading.
+    Relative,
+}
+
+impl ControlScheme {
+    pub const ALL: [ControlScheme; 2] = [ControlScheme::Absolute, ControlScheme::Relative];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ControlScheme::Absolute => "Absolute",
+            ControlScheme::Relative => "Relative (left/right)",
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: ThemeKind::default(),
+            sound_volume: 7,
+            music_volume: 5,
+            muted: false,
+            stick_deadzone: 5,
+            touch_buttons: false,
+            key_bindings: KeyBindings::default(),
+            control_scheme: ControlScheme::default(),
+            input_buffer_size: 3,
+            input_buffering: InputBuffering::default(),
+            reject_queued_reversals: false,
+            food_particles: true,
+            death_explosion: true,
+            score_popups: true,
+            growth_pulse: true,
+            screen_shake: true,
+            smooth_movement: true,
+            camera_mode: CameraMode::default(),
+            view_cells: 20,
+            minimap_corner: Corner::TopRight,
+            minimap_size: 20,
+            game_mode: GameMode::default(),
+            campaign_level: 0,
+            board_size: 20,
+            world_mode: WorldMode::default(),
+            topology: Topology::default(),
+            portal_pairs: 0,
+            hazards: 0,
+            power_ups: false,
+        }
+    }
+}
+
+/// Written whenever the selection or a value on the settings screen changes.
+#[derive(Message)]
+pub struct MenuNavigated;
+
+/// One line on the settings screen.
+struct SettingItem {
+    label: &'static str,
+    value: fn(&Settings) -> String,
+    /// Changes the setting by one step, forwards if `delta` is positive and backwards otherwise.
+    change: fn(&mut Settings, i32),
+    /// The action whose key is captured when this item is changed, instead of calling `change`.
+    rebinds: Option<Action>,
+}
+
+const ITEMS: &[SettingItem] = &[
+    SettingItem {
+        label: "Theme",
+        value: |s| s.theme.name().to_owned(),
+        change: |s, delta| s.theme = cycle(&ThemeKind::ALL, s.theme, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Sound volume",
+        value: |s| volume_text(s.sound_volume),
+        change: |s, delta| s.sound_volume = change_volume(s.sound_volume, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Music volume",
+        value: |s| volume_text(s.music_volume),
+        change: |s, delta| s.music_volume = change_volume(s.music_volume, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Mute",
+        value: |s| format!("{} ({})", on_off(s.muted), binding_text(s, Action::Mute)),
+        change: |s, _| s.muted = !s.muted,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Stick deadzone",
+        value: |s| format!("{}%", s.stick_deadzone as u32 * 10),
+        change: |s, delta| s.stick_deadzone = (s.stick_deadzone as i32 + delta).clamp(1, 9) as u8,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Touch buttons",
+        value: |s| on_off(s.touch_buttons),
+        change: |s, _| s.touch_buttons = !s.touch_buttons,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Food particles",
+        value: |s| on_off(s.food_particles),
+        change: |s, _| s.food_particles = !s.food_particles,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Death explosion",
+        value: |s| on_off(s.death_explosion),
+        change: |s, _| s.death_explosion = !s.death_explosion,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Score popups",
+        value: |s| on_off(s.score_popups),
+        change: |s, _| s.score_popups = !s.score_popups,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Growth pulse",
+        value: |s| on_off(s.growth_pulse),
+        change: |s, _| s.growth_pulse = !s.growth_pulse,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Screen shake",
+        value: |s| on_off(s.screen_shake),
+        change: |s, _| s.screen_shake = !s.screen_shake,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Smooth movement",
+        value: |s| on_off(s.smooth_movement),
+        change: |s, _| s.smooth_movement = !s.smooth_movement,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Mode",
+        value: |s| s.game_mode.name().to_owned(),
+        change: |s, delta| s.game_mode = cycle(&GameMode::ALL, s.game_mode, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Campaign level",
+        value: |s| {
+            format!(
+                "{}: {}",
+                s.campaign_level + 1,
+                campaign::level(s.campaign_level).name
+            )
+        },
+        change: |s, delta| {
+            s.campaign_level =
+                (s.campaign_level as i32 + delta).rem_euclid(LEVELS.len() as i32) as usize
+        },
+        rebinds: None,
+    },
+    SettingItem {
+        label: "World",
+        value: |s| s.world_mode.name().to_owned(),
+        change: |s, delta| s.world_mode = cycle(&WorldMode::ALL, s.world_mode, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Grid",
+        value: |s| s.topology.name().to_owned(),
+        change: |s, delta| s.topology = cycle(&Topology::ALL, s.topology, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Portals",
+        value: |s| match s.portal_pairs {
+            0 => "Off".to_owned(),
+            1 => "1 pair".to_owned(),
+            pairs => format!("{pairs} pairs"),
+        },
+        change: |s, delta| s.portal_pairs = (s.portal_pairs as i32 + delta).clamp(0, 4) as u8,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Hazards",
+        value: |s| match s.hazards {
+            0 => "Off".to_owned(),
+            hazards => hazards.to_string(),
+        },
+        change: |s, delta| s.hazards = (s.hazards as i32 + 3 * delta).clamp(0, 15) as u8,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Power-ups",
+        value: |s| on_off(s.power_ups),
+        change: |s, _| s.power_ups = !s.power_ups,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Board size",
+        value: |s| format!("{0}x{0}", s.board_size),
+        change: |s, delta| s.board_size = cycle(&BOARD_SIZES, s.board_size, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Camera",
+        value: |s| s.camera_mode.name().to_owned(),
+        change: |s, delta| s.camera_mode = cycle(&CameraMode::ALL, s.camera_mode, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Zoom (mouse wheel)",
+        value: |s| format!("{} cells", s.view_cells),
+        change: |s, delta| s.change_view_cells(-delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Minimap",
+        value: |s| s.minimap_corner.name().to_owned(),
+        change: |s, delta| s.minimap_corner = cycle(&Corner::ALL, s.minimap_corner, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Minimap size",
+        value: |s| format!("{}%", s.minimap_size),
+        change: |s, delta| {
+            let (min, max) = MINIMAP_SIZES;
+            s.minimap_size =
+                (s.minimap_size as i32 + 5 * delta).clamp(min as i32, max as i32) as u8;
+        },
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Steering",
+        value: |s| s.control_scheme.name().to_owned(),
+        change: |s, delta| s.control_scheme = cycle(&ControlScheme::ALL, s.control_scheme, delta),
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Turn buffering",
+        value: |s| s.input_buffering.name().to_owned(),
+        change: |s, delta| {
+            s.input_buffering = cycle(&InputBuffering::ALL, s.input_buffering, delta)
+        },
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Turn buffer size",
+        value: |s| s.input_buffer_size.to_string(),
+        change: |s, delta| {
+            let (min, max) = INPUT_BUFFER_SIZES;
+            s.input_buffer_size =
+                (s.input_buffer_size as i32 + delta).clamp(min as i32, max as i32) as u8
+        },
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Reject queued reversals",
+        value: |s| on_off(s.reject_queued_reversals),
+        change: |s, _| s.reject_queued_reversals = !s.reject_queued_reversals,
+        rebinds: None,
+    },
+    SettingItem {
+        label: "Controls",
+        value: |s| {
+            s.key_bindings
+                .preset()
+                .map_or("Custom", Preset::name)
+                .to_owned()
+        },
+        change: |s, delta| {
+            let current = s.key_bindings.preset().unwrap_or(Preset::Arrows);
+            s.key_bindings = cycle(&Preset::ALL, current, delta).bindings();
+        },
+        rebinds: None,
+    },
+    key_binding_item(Action::TurnUp),
+    key_binding_item(Action::TurnDown),
+    key_binding_item(Action::TurnLeft),
+    key_binding_item(Action::TurnRight),
+    key_binding_item(Action::TurnUpLeft),
+    key_binding_item(Action::TurnUpRight),
+    key_binding_item(Action::TurnDownLeft),
+    key_binding_item(Action::TurnDownRight),
+    key_binding_item(Action::Pause),
+    key_binding_item(Action::Restart),
+    key_binding_item(Action::Quit),
+    key_binding_item(Action::Mute),
+];
+
+/// An item showing the key bound to `action`, which is rebound to the next key pressed when the
+/// item is changed.
+const fn key_binding_item(action: Action) -> SettingItem {
+    SettingItem {
+        label: action.name(),
+        // The key is looked up by `update_settings_screen`.
+        value: |_| String::new(),
+        change: |_, _| {},
+        rebinds: Some(action),
+    }
+}
+
+fn binding_text(settings: &Settings, action: Action) -> String {
+    settings
+        .key_bindings
+        .key(action)
+        .map_or_else(|| "-".to_owned(), key_name)
+}
+
+fn volume_text(volume: u8) -> String {
+    format!("{}%", volume as u32 * 100 / MAX_VOLUME as u32)
+}
+
+fn change_volume(volume: u8, delta: i32) -> u8 {
+    (volume as i32 + delta).clamp(0, MAX_VOLUME as i32) as u8
+}
+
+fn on_off(value: bool) -> String {
+    if value { "On" } else { "Off" }.to_owned()
+}
+
+/// Returns the element `delta` steps after `current` in `all`, wrapping around at both ends.
+pub fn cycle<T: Copy + PartialEq>(all: &[T], current: T, delta: i32) -> T {
+    let i = all.iter().position(|&x| x == current).unwrap_or(0) as i32;
+    all[(i + delta).rem_euclid(all.len() as i32) as usize]
+}
+
+/// How many entries of [`ITEMS`] fit on the screen at once.
+const VISIBLE_ITEMS: usize = 12;
+
+/// Index of the selected entry in [`ITEMS`].
+#[derive(Resource)]
+struct SelectedItem(usize);
+
+#[derive(Component)]
+struct SettingsEntry(usize);
+
+/// Marker for the text on the settings screen that is not a [`SettingsEntry`].
+#[derive(Component)]
+struct SettingsText;
+
+fn show_settings_screen(mut commands: Commands, theme: Res<ActiveTheme>) {
+    commands.insert_resource(SelectedItem(0));
+    let font = TextFont {
+        font: theme.font.clone(),
+        font_size: 30.0,
+        ..default()
+    };
+    commands
+        .spawn((Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },))
+        .with_children(|parent| {
+            parent
+                .spawn((Text::new(""), TextLayout::new_with_justify(Justify::Center)))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextSpan::new("Settings\n"),
+                        TextFont {
+                            font_size: 60.0,
+                            ..font.clone()
+                        },
+                        TextColor(theme.text),
+                        SettingsText,
+                    ));
+                    for i in 0..ITEMS.len() {
+                        parent.spawn((
+                            TextSpan::default(), // Updated later
+                            font.clone(),
+                            TextColor(theme.text),
+                            SettingsEntry(i),
+                        ));
+                    }
+                    parent.spawn((
+                        TextSpan::new(
+                            "\n\nUp/Down: select   Left/Right: change or rebind   Restart/Start/tap: play\nE: level editor",
+                        ),
+                        TextFont {
+                            font_size: 25.0,
+                            ..font.clone()
+                        },
+                        TextColor(theme.text),
+                        SettingsText,
+                    ));
+                });
+        });
+}
+
+/// Keys that navigate the settings screen in addition to the bound turn actions.
+const MENU_KEYS: [(KeyCode, Dir); 4] = [
+    (KeyCode::ArrowUp, Dir::Up),
+    (KeyCode::ArrowDown, Dir::Down),
+    (KeyCode::ArrowLeft, Dir::Left),
+    (KeyCode::ArrowRight, Dir::Right),
+];
+
+fn read_settings_input(
+    actions: Res<Actions>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut selected: ResMut<SelectedItem>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut navigated: MessageWriter<MenuNavigated>,
+) {
+    // Don't act on the key that was just captured.
+    if rebinding.0.is_some() || rebinding.is_changed() {
+        return;
+    }
+    let item = &ITEMS[selected.0];
+    // The arrow keys always navigate, so that the menu stays usable whatever the bindings are.
+    let mut dirs: Vec<Dir> = Vec::new();
+    let arrows = MENU_KEYS
+        .iter()
+        .filter(|(key, _)| keys.just_pressed(*key))
+        .map(|(_, dir)| dir.clone());
+    for dir in actions.iter().filter_map(Action::dir).chain(arrows) {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    for dir in dirs {
+        match dir {
+            Dir::Up => selected.0 = (selected.0 + ITEMS.len() - 1) % ITEMS.len(),
+            Dir::Down => selected.0 = (selected.0 + 1) % ITEMS.len(),
+            Dir::Left | Dir::Right if item.rebinds.is_some() => rebinding.0 = item.rebinds,
+            Dir::Left => (item.change)(&mut settings, -1),
+            Dir::Right => (item.change)(&mut settings, 1),
+            // The diagonals only steer on a hex grid.
+            _ => continue,
+        }
+        navigated.write(MenuNavigated);
+    }
+    if actions.just_pressed(Action::Restart)
+        || actions.just_pressed(Action::Pause)
+        || keys.just_pressed(KeyCode::Tab)
+    {
+        next_state.set(GameState::InGame);
+    } else if keys.just_pressed(KeyCode::KeyE) {
+        next_state.set(GameState::Editor);
+    }
+}
+
+fn update_settings_screen(
+    settings: Res<Settings>,
+    selected: Res<SelectedItem>,
+    rebinding: Res<Rebinding>,
+    theme: Res<ActiveTheme>,
+    mut entries: Query<
+        (&SettingsEntry, &mut TextSpan, &mut TextFont, &mut TextColor),
+        Without<SettingsText>,
+    >,
+    mut texts: Query<(&mut TextFont, &mut TextColor), With<SettingsText>>,
+) {
+    if !settings.is_changed() && !selected.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (mut font, mut color) in texts.iter_mut() {
+        font.font = theme.font.clone();
+        color.0 = theme.text;
+    }
+    // Scroll so that the selected item stays in the middle of the visible ones where possible.
+    let first_visible = selected
+        .0
+        .saturating_sub(VISIBLE_ITEMS / 2)
+        .min(ITEMS.len() - VISIBLE_ITEMS);
+    for (entry, mut span, mut font, mut color) in entries.iter_mut() {
+        if !(first_visible..first_visible + VISIBLE_ITEMS).contains(&entry.0) {
+            span.0.clear();
+            continue;
+        }
+        let item = &ITEMS[entry.0];
+        let value = match item.rebinds {
+            Some(action) if rebinding.0 == Some(action) => "press a key".to_owned(),
+            Some(action) => binding_text(&settings, action),
+            None => (item.value)(&settings),
+        };
+        span.0 = format!("\n{}: < {} >", item.label, value);
+        font.font = theme.font.clone();
+        color.0 = if entry.0 == selected.0 {
+            theme.highlight
+        } else {
+            theme.text
+        };
+    }
+}
+
+/// Keeps the board and the way it is drawn in [`Config`] in line with the settings.
+fn apply_board_settings(
+    settings: Res<Settings>,
+    custom: Res<CustomLevel>,
+    mut config: ResMut<Config>,
+) {
+    if settings.is_changed() {
+        sync_board_config(&settings, &custom, &mut config);
+        if config.smooth_movement != settings.smooth_movement {
+            config.smooth_movement = settings.smooth_movement;
+        }
+    }
+}
+
+/// Sets the size and shape of the board in `config` to those of the game mode in `settings`.
+pub fn sync_board_config(settings: &Settings, custom: &CustomLevel, config: &mut Config) {
+    let (size, topology) = match settings.game_mode {
+        GameMode::Endless | GameMode::TimeAttack => {
+            (IVec2::splat(settings.board_size), settings.topology)
+        }
+        GameMode::Campaign => (
+            campaign::level(settings.campaign_level).size(),
+            Topology::Square,
+        ),
+        GameMode::Daily => (
+            IVec2::splat(daily::rules(daily::today()).board_size),
+            Topology::Square,
+        ),
+        GameMode::Custom => (level::size(&custom.0.rows), Topology::Square),
+    };
+    if config.grid_size_x != size.x || config.grid_size_y != size.y {
+        config.grid_size_x = size.x;
+        config.grid_size_y = size.y;
+    }
+    if config.topology != topology {
+        config.topology = topology;
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        storage::save(SETTINGS_KEY, settings.as_ref());
+    }
+}
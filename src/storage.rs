@@ -0,0 +1,101 @@
+//! Persistent key-value storage for settings and progress.
+//!
+//! Values are stored as RON in files in the user's data directory, or in `localStorage` on the web.
+
+use bevy::log::{error, warn};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Loads the value stored under `key`, or `None` if there is none or it cannot be parsed.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let text = platform::read(key)?;
+    match ron::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Ignoring invalid stored value for {key}: {e}");
+            None
+        }
+    }
+}
+
+/// Stores `value` under `key`, replacing any previous value.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(text) => platform::write(key, &text),
+        Err(e) => error!("Failed to serialize {key}: {e}"),
+    }
+}
+
+/// Removes the value stored under `key`, if any.
+pub fn remove(key: &str) {
+    platform::remove(key);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use bevy::log::error;
+    use std::path::PathBuf;
+
+    fn path(key: &str) -> PathBuf {
+        let base = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+            .unwrap_or_default();
+        base.join("snake").join(format!("{key}.ron"))
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        std::fs::read_to_string(path(key)).ok()
+    }
+
+    pub fn write(key: &str, text: &str) {
+        let path = path(key);
+        let result = match path.parent() {
+            Some(dir) => std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, text)),
+            None => std::fs::write(&path, text),
+        };
+        if let Err(e) = result {
+            error!("Failed to write {}: {e}", path.display());
+        }
+    }
+
+    pub fn remove(key: &str) {
+        let path = path(key);
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                error!("Failed to remove {}: {e}", path.display())
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use bevy::log::error;
+
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        storage()?.get_item(&format!("snake.{key}")).ok()?
+    }
+
+    pub fn write(key: &str, text: &str) {
+        let stored = storage().is_some_and(|s| s.set_item(&format!("snake.{key}"), text).is_ok());
+        if !stored {
+            error!("Failed to write {key} to localStorage");
+        }
+    }
+
+    pub fn remove(key: &str) {
+        let removed = storage().is_some_and(|s| s.remove_item(&format!("snake.{key}")).is_ok());
+        if !removed {
+            error!("Failed to remove {key} from localStorage");
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::config::GameState;
+use crate::settings::Settings;
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Theme>()
+            .register_asset_loader(ThemeLoader)
+            .add_systems(Startup, load_themes)
+            .add_systems(
+                Update,
+                (
+                    wait_for_themes.run_if(in_state(GameState::Loading)),
+                    apply_theme.run_if(not(in_state(GameState::Loading))),
+                ),
+            );
+    }
+}
+
+/// The built-in themes that can be selected in the settings.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ThemeKind {
+    #[default]
+    Classic,
+    HighContrast,
+    Colorblind,
+    Light,
+}
+
+impl ThemeKind {
+    pub const ALL: [ThemeKind; 4] = [
+        ThemeKind::Classic,
+        ThemeKind::HighContrast,
+        ThemeKind::Colorblind,
+        ThemeKind::Light,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeKind::Classic => "Classic",
+            ThemeKind::HighContrast => "High contrast",
+            ThemeKind::Colorblind => "Colorblind",
+            ThemeKind::Light => "Light",
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            ThemeKind::Classic => "themes/classic.theme.ron",
+            ThemeKind::HighContrast => "themes/high_contrast.theme.ron",
+            ThemeKind::Colorblind => "themes/colorblind.theme.ron",
+            ThemeKind::Light => "themes/light.theme.ron",
+        }
+    }
+}
+
+/// Colors, font and line width used to draw the game.
+#[derive(Asset, TypePath, Clone)]
+pub struct Theme {
+    pub background: Color,
+    pub grid: Color,
+    pub grid_line_width: f32,
+    pub head_outline: Color,
+    pub head_fill: Color,
+    pub tail: Color,
+    pub food: Color,
+    /// Obstacles inside the board.
+    pub wall: Color,
+    /// Moving walls, spikes and enemies.
+    pub hazard: Color,
+    /// One color for each pair of portals, reused if there are more pairs.
+    pub portals: Vec<Color>,
+    pub text: Color,
+    /// Used for the game over message and the selected menu entry.
+    pub highlight: Color,
+    pub font: Handle<Font>,
+}
+
+impl Theme {
+    /// The color of the `pair`th pair of portals.
+    pub fn portal_color(&self, pair: usize) -> Color {
+        match self.portals.len() {
+            0 => self.highlight,
+            len => self.portals[pair % len],
+        }
+    }
+}
+
+/// The theme selected in the settings, ready to use once the game has left [`GameState::Loading`].
+#[derive(Resource, Deref)]
+pub struct ActiveTheme(Theme);
+
+/// The on-disk format of a theme. Colors are hex strings like `"#FA8072"`.
+#[derive(Deserialize)]
+struct ThemeFile {
+    background: String,
+    grid: String,
+    grid_line_width: f32,
+    head_outline: String,
+    head_fill: String,
+    tail: String,
+    food: String,
+    wall: String,
+    hazard: String,
+    portals: Vec<String>,
+    text: String,
+    highlight: String,
+    font: String,
+}
+
+#[derive(Default, TypePath)]
+struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    type Asset = Theme;
+    type Settings = ();
+    type Error = BevyError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Theme, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: ThemeFile = ron::de::from_bytes(&bytes)?;
+        let color = |hex: &str| Srgba::hex(hex).map(Color::from);
+        Ok(Theme {
+            background: color(&file.background)?,
+            grid: color(&file.grid)?,
+            grid_line_width: file.grid_line_width,
+            head_outline: color(&file.head_outline)?,
+            head_fill: color(&file.head_fill)?,
+            tail: color(&file.tail)?,
+            food: color(&file.food)?,
+            wall: color(&file.wall)?,
+            hazard: color(&file.hazard)?,
+            portals: file
+                .portals
+                .iter()
+                .map(|hex| color(hex))
+                .collect::<Result<_, _>>()?,
+            text: color(&file.text)?,
+            highlight: color(&file.highlight)?,
+            font: load_context.load(file.font),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+/// Handles to all built-in themes.
+#[derive(Resource)]
+pub struct Themes(HashMap<ThemeKind, Handle<Theme>>);
+
+fn load_themes(asset_server: Res<AssetServer>, mut commands: Commands) {
+    let themes = ThemeKind::ALL
+        .iter()
+        .map(|&kind| (kind, asset_server.load(kind.path())))
+        .collect();
+    commands.insert_resource(Themes(themes));
+}
+
+fn wait_for_themes(
+    asset_server: Res<AssetServer>,
+    themes: Res<Themes>,
+    assets: Res<Assets<Theme>>,
+    settings: Res<Settings>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (kind, handle) in themes.0.iter() {
+        if asset_server.load_state(handle).is_failed() {
+            panic!("Failed to load theme {}", kind.path());
+        }
+        if !asset_server.is_loaded_with_dependencies(handle) {
+            return;
+        }
+    }
+    let theme = assets.get(&themes.0[&settings.theme]).unwrap().clone();
+    commands.insert_resource(ClearColor(theme.background));
+    commands.insert_resource(ActiveTheme(theme));
+    next_state.set(GameState::InGame);
+}
+
+pub fn apply_theme(
+    themes: Res<Themes>,
+    assets: Res<Assets<Theme>>,
+    settings: Res<Settings>,
+    mut active: ResMut<ActiveTheme>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    active.0 = assets.get(&themes.0[&settings.theme]).unwrap().clone();
+    clear_color.0 = active.background;
+}
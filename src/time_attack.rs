@@ -0,0 +1,264 @@
+//! A round against a fixed clock, in which the goal is to eat as much food as possible. Bonus food
+//! adds time to the clock.
+//!
+//! The clock only runs in [`GameState::InGame`] and uses virtual time, so it stops while the game
+//! is paused, on the settings screen and after the round has ended.
+
+use crate::board::{self, Board, Cell};
+use crate::config::{GameState, Position};
+use crate::food::{self, FoodAssets};
+use crate::player::{self, DeathCause, Player, PlayerDied, SnakeSystems};
+use crate::score::ScoreUpdate;
+use crate::settings::{GameMode, Settings};
+use crate::storage;
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub struct TimeAttackPlugin;
+
+impl Plugin for TimeAttackPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load::<HighScores>(HIGH_SCORES_KEY).unwrap_or_default())
+            .add_systems(
+                OnEnter(GameState::InGame),
+                init.after(board::init).after(player::init),
+            )
+            .add_systems(
+                Update,
+                (
+                    tick_clock.in_set(SnakeSystems::Collide),
+                    collect_bonus.in_set(SnakeSystems::Collide),
+                    count_food.after(SnakeSystems::Collide),
+                    record_score.after(count_food),
+                    spawn_bonus,
+                    expire_bonus,
+                    update_hud.after(count_food),
+                )
+                    .run_if(in_state(GameState::InGame).and(resource_exists::<TimeAttack>)),
+            )
+            .add_systems(Update, save_high_scores);
+    }
+}
+
+const HIGH_SCORES_KEY: &str = "time_attack";
+
+/// How long a round lasts without bonus time.
+const ROUND_TIME: Duration = Duration::from_secs(60);
+
+/// The time added by eating bonus food.
+const BONUS_TIME: Duration = Duration::from_secs(5);
+
+/// How often bonus food appears, if there is none on the board.
+const BONUS_INTERVAL: f32 = 10.0;
+
+/// How long bonus food stays on the board.
+const BONUS_LIFETIME: f32 = 6.0;
+
+/// How many scores the high-score table keeps.
+const HIGH_SCORE_COUNT: usize = 10;
+
+/// The best numbers of food eaten in a round, highest first, stored between sessions.
+#[derive(Resource, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct HighScores(Vec<u32>);
+
+impl HighScores {
+    /// Adds `score` to the table and returns its place in it, or `None` if it is not good enough.
+    fn record(&mut self, score: u32) -> Option<usize> {
+        let rank = self.0.partition_point(|&other| other >= score);
+        if rank >= HIGH_SCORE_COUNT {
+            return None;
+        }
+        self.0.insert(rank, score);
+        self.0.truncate(HIGH_SCORE_COUNT);
+        Some(rank)
+    }
+}
+
+/// The state of a time attack round.
+#[derive(Resource)]
+struct TimeAttack {
+    clock: Timer,
+    food_eaten: u32,
+    bonus_timer: Timer,
+    bonus_material: Handle<ColorMaterial>,
+    rng: StdRng,
+}
+
+/// Inserted when a time attack round ends, for the screen shown after it.
+#[derive(Resource)]
+pub struct TimeAttackResult {
+    pub food_eaten: u32,
+    /// The high-score table, including this round if it made it in.
+    pub high_scores: Vec<u32>,
+    /// The place of this round in `high_scores`.
+    pub rank: Option<usize>,
+}
+
+/// Food that adds [`BONUS_TIME`] to the clock when eaten, and disappears when not eaten in time.
+#[derive(Component)]
+struct BonusFood {
+    lifetime: Timer,
+}
+
+/// Marker for the text showing the time left and the food eaten.
+#[derive(Component)]
+struct TimeAttackHud;
+
+fn init(
+    settings: Res<Settings>,
+    theme: Res<ActiveTheme>,
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.remove_resource::<TimeAttackResult>();
+    if settings.game_mode != GameMode::TimeAttack {
+        commands.remove_resource::<TimeAttack>();
+        return;
+    }
+    commands.insert_resource(TimeAttack {
+        clock: Timer::new(ROUND_TIME, TimerMode::Once),
+        food_eaten: 0,
+        bonus_timer: Timer::from_seconds(BONUS_INTERVAL, TimerMode::Repeating),
+        bonus_material: materials.add(theme.highlight),
+        rng: StdRng::seed_from_u64(getrandom::u64().unwrap_or(0)),
+    });
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: theme.font.clone(),
+            font_size: 30.0,
+            ..default()
+        },
+        TextColor(theme.text),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(50.0),
+            bottom: Val::Px(5.0),
+            ..default()
+        },
+        TimeAttackHud,
+    ));
+}
+
+/// Ends the round when the clock runs out.
+fn tick_clock(
+    time: Res<Time>,
+    mut attack: ResMut<TimeAttack>,
+    mut died: MessageWriter<PlayerDied>,
+) {
+    if attack.clock.tick(time.delta()).just_finished() {
+        died.write(PlayerDied {
+            cause: DeathCause::OutOfTime,
+        });
+    }
+}
+
+fn collect_bonus(
+    head: Query<&Position, With<Player>>,
+    bonus: Query<(), With<BonusFood>>,
+    board: Res<Board>,
+    mut attack: ResMut<TimeAttack>,
+) {
+    let Ok(head) = head.single() else {
+        return;
+    };
+    if let Cell::Food(food) = board.at(head)
+        && bonus.contains(food)
+    {
+        let duration = attack.clock.duration() + BONUS_TIME;
+        attack.clock.set_duration(duration);
+    }
+}
+
+fn count_food(mut updates: MessageReader<ScoreUpdate>, mut attack: ResMut<TimeAttack>) {
+    for update in updates.read() {
+        if let ScoreUpdate::AteFood { .. } = update {
+            attack.food_eaten += 1;
+        }
+    }
+}
+
+/// Enters the round into the high-score table when it ends, whether the clock ran out or the snake
+/// died.
+fn record_score(
+    mut died: MessageReader<PlayerDied>,
+    attack: Res<TimeAttack>,
+    mut high_scores: ResMut<HighScores>,
+    mut commands: Commands,
+) {
+    if died.read().count() == 0 {
+        return;
+    }
+    let rank = high_scores.record(attack.food_eaten);
+    commands.insert_resource(TimeAttackResult {
+        food_eaten: attack.food_eaten,
+        high_scores: high_scores.0.clone(),
+        rank,
+    });
+}
+
+fn spawn_bonus(
+    time: Res<Time>,
+    food_assets: Res<FoodAssets>,
+    head: Query<&Position, With<Player>>,
+    bonus: Query<(), With<BonusFood>>,
+    mut attack: ResMut<TimeAttack>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    if !attack.bonus_timer.tick(time.delta()).just_finished() || !bonus.is_empty() {
+        return;
+    }
+    let Ok(head) = head.single() else {
+        return;
+    };
+    let chunk = board.chunk_of(head);
+    let Some(pos) = board.random_free(chunk, &mut attack.rng) else {
+        return;
+    };
+    let food = food::spawn_at(&mut commands, &food_assets, pos.clone());
+    commands.entity(food).insert((
+        MeshMaterial2d(attack.bonus_material.clone()),
+        BonusFood {
+            lifetime: Timer::from_seconds(BONUS_LIFETIME, TimerMode::Once),
+        },
+    ));
+    board.set(&pos, Cell::Food(food));
+}
+
+fn expire_bonus(
+    time: Res<Time>,
+    mut bonus: Query<(Entity, &mut BonusFood, &Position)>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+) {
+    for (entity, mut food, pos) in bonus.iter_mut() {
+        if food.lifetime.tick(time.delta()).just_finished() {
+            board.vacate(pos, entity);
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_hud(attack: Res<TimeAttack>, mut hud: Query<&mut Text, With<TimeAttackHud>>) {
+    let text = format!(
+        "Time: {:.1}s   Food: {}",
+        attack.clock.remaining_secs(),
+        attack.food_eaten
+    );
+    for mut hud in hud.iter_mut() {
+        if hud.0 != text {
+            hud.0.clone_from(&text);
+        }
+    }
+}
+
+fn save_high_scores(high_scores: Res<HighScores>) {
+    if high_scores.is_changed() && !high_scores.is_added() {
+        storage::save(HIGH_SCORES_KEY, high_scores.as_ref());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::config::{Dir, GameState};
+use crate::settings::Settings;
+use crate::theme::ActiveTheme;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::collections::HashMap;
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<TouchInput>()
+            .init_resource::<SwipeAnchors>()
+            .add_systems(OnEnter(GameState::InGame), spawn_touch_buttons)
+            .add_systems(
+                Update,
+                (
+                    clear_anchors.run_if(state_changed::<GameState>),
+                    read_swipes.after(clear_anchors),
+                    read_touch_buttons.run_if(in_state(GameState::InGame)),
+                    layout_touch_buttons,
+                ),
+            );
+    }
+}
+
+/// Input from the touch screen, already interpreted as a swipe, a tap or an on-screen button.
+#[derive(Message, Clone)]
+pub enum TouchInput {
+    /// A swipe or a press on one of the on-screen arrow buttons.
+    Steer(Dir),
+    Tap,
+}
+
+/// How far a finger has to move in logical pixels to count as a swipe instead of a tap.
+const SWIPE_DISTANCE: f32 = 30.0;
+
+/// Where each active touch started its current swipe and whether it has swiped already.
+///
+/// Touches that started before the last state change are not tracked, so that the finger that
+/// pressed a button to leave a screen does not trigger anything on the next one.
+#[derive(Default, Resource)]
+struct SwipeAnchors(HashMap<u64, (Vec2, bool)>);
+
+fn clear_anchors(mut anchors: ResMut<SwipeAnchors>) {
+    anchors.0.clear();
+}
+
+fn read_swipes(
+    touches: Res<Touches>,
+    mut anchors: ResMut<SwipeAnchors>,
+    mut input: MessageWriter<TouchInput>,
+) {
+    for touch in touches.iter_just_pressed() {
+        anchors.0.insert(touch.id(), (touch.position(), false));
+    }
+    for touch in touches.iter() {
+        let Some((anchor, swiped)) = anchors.0.get_mut(&touch.id()) else {
+            continue;
+        };
+        let delta = touch.position() - *anchor;
+        if delta.length() < SWIPE_DISTANCE {
+            continue;
+        }
+        // Screen coordinates grow downwards.
+        let dir = if delta.x.abs() > delta.y.abs() {
+            if delta.x > 0.0 { Dir::Right } else { Dir::Left }
+        } else if delta.y > 0.0 {
+            Dir::Down
+        } else {
+            Dir::Up
+        };
+        input.write(TouchInput::Steer(dir));
+        // Start the next swipe from here so that one long drag can steer several times.
+        *anchor = touch.position();
+        *swiped = true;
+    }
+    for touch in touches.iter_just_released() {
+        if let Some((_, false)) = anchors.0.remove(&touch.id()) {
+            input.write(TouchInput::Tap);
+        }
+    }
+    for touch in touches.iter_just_canceled() {
+        anchors.0.remove(&touch.id());
+    }
+}
+
+/// The container of the on-screen arrow buttons.
+#[derive(Component)]
+struct TouchButtons;
+
+#[derive(Component)]
+struct TouchButton(Dir);
+
+fn spawn_touch_buttons(settings: Res<Settings>, theme: Res<ActiveTheme>, mut commands: Commands) {
+    if !settings.touch_buttons {
+        return;
+    }
+    let button = |dir: Dir, label: &str| {
+        (
+            Button,
+            Node {
+                width: Val::Px(70.0),
+                height: Val::Px(70.0),
+                margin: UiRect::all(Val::Px(4.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(theme.text.with_alpha(0.25)),
+            TouchButton(dir),
+            children![(
+                Text::new(label),
+                TextFont {
+                    font: theme.font.clone(),
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(theme.text),
+            )],
+        )
+    };
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        TouchButtons,
+        children![
+            button(Dir::Up, "↑"),
+            (
+                Node::default(),
+                children![
+                    button(Dir::Left, "←"),
+                    button(Dir::Down, "↓"),
+                    button(Dir::Right, "→"),
+                ],
+            ),
+        ],
+    ));
+}
+
+fn read_touch_buttons(
+    buttons: Query<(&Interaction, &TouchButton), Changed<Interaction>>,
+    mut input: MessageWriter<TouchInput>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            input.write(TouchInput::Steer(button.0.clone()));
+        }
+    }
+}
+
+/// Places the buttons below the board in portrait orientation and to its right otherwise.
+fn layout_touch_buttons(
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut buttons: Query<&mut Node, With<TouchButtons>>,
+) {
+    let window = window.single().unwrap();
+    let (left, right, margin_left) = if window.height() > window.width() {
+        // Centered; the margin is half the width of the buttons.
+        (Val::Percent(50.0), Val::Auto, Val::Px(-117.0))
+    } else {
+        (Val::Auto, Val::Px(20.0), Val::Px(0.0))
+    };
+    for mut node in buttons.iter_mut() {
+        // Avoid triggering a new UI layout every frame.
+        if node.left != left || node.right != right || node.margin.left != margin_left {
+            node.left = left;
+            node.right = right;
+            node.margin.left = margin_left;
+            node.bottom = Val::Px(20.0);
+        }
+    }
+}